@@ -0,0 +1,416 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use csv::{ByteRecord, StringRecord};
+use linked_hash_map::LinkedHashMap;
+use serde::Deserialize;
+
+use crate::decimal::Decimal;
+use crate::sort::{typed_value, SortType, SortValue};
+
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Function {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+    First,
+    Last,
+    CountDistinct,
+}
+
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Aggregate {
+    pub function: Function,
+
+    /// Output column to aggregate. Without it, `count` counts rows.
+    pub column: Option<String>,
+
+    /// How `min` and `max` compare values. If omitted, they compare numbers if every
+    /// value is one, and text otherwise.
+    #[serde(rename = "type")]
+    pub value_type: Option<SortType>,
+
+    /// Format of dates, if `type` is `date`.
+    pub format: Option<String>,
+}
+
+
+/// The smallest or the largest value so far, along with its typed form.
+type Candidate = Option<(SortValue, Vec<u8>)>;
+
+
+/// Running state of `min` or `max`.
+struct Extreme {
+    /// Which end to keep: `Less` for the minimum, `Greater` for the maximum.
+    end: Ordering,
+
+    /// By the given type, or by number if no type is given.
+    typed: Candidate,
+
+    /// By text, used if no type is given and some value is not a number.
+    text: Candidate,
+    all_numbers: bool,
+}
+
+
+fn offer(candidate: &mut Candidate, typed: SortValue, value: &[u8], end: Ordering) {
+    if candidate.as_ref().is_none_or(|(current, _)| typed.cmp(current) == end) {
+        *candidate = Some((typed, value.to_vec()));
+    }
+}
+
+
+impl Extreme {
+    fn new(end: Ordering) -> Extreme {
+        Extreme { end, typed: None, text: None, all_numbers: true }
+    }
+
+    fn add(&mut self, value: &[u8], aggregate: &Aggregate) {
+        match aggregate.value_type {
+            Some(value_type) => if let Some(typed) = typed_value(
+                value_type,
+                aggregate.format.as_deref(),
+                value,
+            ) {
+                offer(&mut self.typed, typed, value, self.end);
+            },
+
+            None => {
+                match typed_value(SortType::Number, None, value) {
+                    Some(number) => offer(&mut self.typed, number, value, self.end),
+                    None => self.all_numbers = false,
+                }
+
+                offer(&mut self.text, SortValue::String(value.to_vec()), value, self.end);
+            },
+        }
+    }
+
+    fn result(&self) -> Vec<u8> {
+        let candidate = if self.all_numbers { &self.typed } else { &self.text };
+
+        candidate.as_ref().map(|(_, value)| value.clone()).unwrap_or_default()
+    }
+}
+
+
+/// Running state of one aggregate in one group.
+enum Accumulator {
+    Count(usize),
+    Sum(Option<Decimal>),
+    Avg(Decimal, usize),
+    Min(Extreme),
+    Max(Extreme),
+    First(Option<Vec<u8>>),
+    Last(Option<Vec<u8>>),
+    CountDistinct(HashSet<Vec<u8>>),
+}
+
+
+fn parse_number(value: &[u8]) -> Option<Decimal> {
+    Decimal::parse(std::str::from_utf8(value).ok()?)
+}
+
+
+impl Accumulator {
+    fn new(function: Function) -> Accumulator {
+        match function {
+            Function::Count => Accumulator::Count(0),
+            Function::Sum => Accumulator::Sum(None),
+            Function::Avg => Accumulator::Avg(Decimal::default(), 0),
+            Function::Min => Accumulator::Min(Extreme::new(Ordering::Less)),
+            Function::Max => Accumulator::Max(Extreme::new(Ordering::Greater)),
+            Function::First => Accumulator::First(None),
+            Function::Last => Accumulator::Last(None),
+            Function::CountDistinct => Accumulator::CountDistinct(HashSet::new()),
+        }
+    }
+
+    /// Add a value. `None` means the row is counted but has no column to aggregate.
+    /// Empty values and values which cannot be parsed as the column type are ignored,
+    /// and so are numbers too large to add up exactly.
+    fn add(&mut self, value: Option<&[u8]>, aggregate: &Aggregate) {
+        let value = match value {
+            None => {
+                if let Accumulator::Count(count) = self {
+                    *count += 1;
+                }
+                return;
+            },
+            Some([]) => return,
+            Some(value) => value,
+        };
+
+        match self {
+            Accumulator::Count(count) => *count += 1,
+
+            Accumulator::Sum(sum) => if let Some(total) = parse_number(value).and_then(
+                |number| number.checked_add(sum.unwrap_or_default()),
+            ) {
+                *sum = Some(total);
+            },
+
+            Accumulator::Avg(sum, count) => if let Some(total) = parse_number(value).and_then(
+                |number| number.checked_add(*sum),
+            ) {
+                *sum = total;
+                *count += 1;
+            },
+
+            Accumulator::Min(extreme) | Accumulator::Max(extreme) => extreme.add(value, aggregate),
+
+            Accumulator::First(first) => if first.is_none() {
+                *first = Some(value.to_vec());
+            },
+
+            Accumulator::Last(last) => *last = Some(value.to_vec()),
+
+            Accumulator::CountDistinct(values) => if !values.contains(value) {
+                values.insert(value.to_vec());
+            },
+        }
+    }
+
+    fn result(&self) -> Vec<u8> {
+        match self {
+            Accumulator::Count(count) => count.to_string().into_bytes(),
+            Accumulator::Sum(sum) => sum.map(|sum| sum.to_string().into_bytes()).unwrap_or_default(),
+
+            Accumulator::Avg(sum, count) => sum.checked_div(*count).map(
+                |average| average.to_string().into_bytes(),
+            ).unwrap_or_default(),
+
+            Accumulator::Min(extreme) | Accumulator::Max(extreme) => extreme.result(),
+
+            Accumulator::First(value) | Accumulator::Last(value) => value.clone().unwrap_or_default(),
+            Accumulator::CountDistinct(values) => values.len().to_string().into_bytes(),
+        }
+    }
+}
+
+
+type Key = Vec<Vec<u8>>;
+
+
+/// Collapses the output rows into one row per group: the key columns followed by
+/// the aggregates. Groups are output in the order they first appear in.
+pub struct Aggregator {
+    keys: Vec<String>,
+    aggregates: LinkedHashMap<String, Aggregate>,
+    key_indices: Vec<usize>,
+    column_indices: Vec<Option<usize>>,
+    groups: LinkedHashMap<Key, Vec<Accumulator>>,
+}
+
+
+impl Aggregator {
+    pub fn new(keys: Vec<String>, aggregates: LinkedHashMap<String, Aggregate>) -> Aggregator {
+        Aggregator {
+            keys,
+            aggregates,
+            key_indices: vec![],
+            column_indices: vec![],
+            groups: LinkedHashMap::new(),
+        }
+    }
+
+    /// Find the key and aggregated columns among the output headers of the current input
+    /// file. Returns the headers of the aggregated output.
+    pub fn resolve(&mut self, headers: &StringRecord) -> Result<StringRecord, String> {
+        let position = |column: &String| headers.iter().position(
+            |header| header == column,
+        ).ok_or(format!(
+            "Column '{}' to group by or aggregate is not among the output columns.",
+            column,
+        ));
+
+        self.key_indices = self.keys.iter().map(position).collect::<Result<_, String>>()?;
+
+        self.column_indices = self.aggregates.iter().map(
+            |(name, aggregate)| match (&aggregate.column, aggregate.function) {
+                (Some(column), _) => position(column).map(Some),
+                (None, Function::Count) => Ok(None),
+                (None, _) => Err(format!("Aggregate '{}' needs a column.", name)),
+            }
+        ).collect::<Result<_, String>>()?;
+
+        Ok(self.keys.iter().chain(self.aggregates.keys()).collect())
+    }
+
+    pub fn push(&mut self, record: ByteRecord) {
+        let key: Key = self.key_indices.iter().map(
+            |index| record.get(*index).unwrap_or(b"").to_vec(),
+        ).collect();
+
+        if !self.groups.contains_key(&key) {
+            let accumulators = self.aggregates.values().map(
+                |aggregate| Accumulator::new(aggregate.function),
+            ).collect();
+
+            self.groups.insert(key.clone(), accumulators);
+        }
+
+        let accumulators = self.groups.get_mut(&key).unwrap();
+
+        for ((accumulator, aggregate), column_index) in accumulators.iter_mut().zip(
+            self.aggregates.values(),
+        ).zip(
+            self.column_indices.iter(),
+        ) {
+            let value = column_index.map(
+                |index| record.get(index).unwrap_or(b""),
+            );
+
+            accumulator.add(value, aggregate);
+        }
+    }
+
+    pub fn finish(
+        self,
+        emit: &mut dyn FnMut(ByteRecord) -> Result<(), String>,
+    ) -> Result<(), String> {
+        for (key, accumulators) in self.groups {
+            let mut record: ByteRecord = key.iter().collect();
+
+            for accumulator in accumulators.iter() {
+                record.push_field(&accumulator.result());
+            }
+
+            emit(record)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregate(function: Function, column: Option<&str>, value_type: Option<SortType>) -> Aggregate {
+        Aggregate {
+            function,
+            column: column.map(|column| column.to_string()),
+            value_type,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let mut aggregates = LinkedHashMap::new();
+        aggregates.insert("rows".to_string(), aggregate(Function::Count, None, None));
+        aggregates.insert("total".to_string(), aggregate(Function::Sum, Some("amount"), None));
+        aggregates.insert("average".to_string(), aggregate(Function::Avg, Some("amount"), None));
+        aggregates.insert("largest".to_string(), aggregate(Function::Max, Some("amount"), Some(SortType::Number)));
+        aggregates.insert("customers".to_string(), aggregate(Function::CountDistinct, Some("customer"), None));
+        aggregates.insert("last".to_string(), aggregate(Function::Last, Some("customer"), None));
+
+        let mut aggregator = Aggregator::new(vec!["region".to_string()], aggregates);
+        let headers = aggregator.resolve(
+            &StringRecord::from(vec!["region", "customer", "amount"]),
+        ).unwrap();
+
+        assert_eq!(
+            headers,
+            StringRecord::from(vec!["region", "rows", "total", "average", "largest", "customers", "last"]),
+        );
+
+        let rows = vec![
+            vec!["North", "ann", "9"],
+            vec!["South", "bob", "100"],
+            vec!["North", "ann", "11"],
+            vec!["North", "cid", ""],
+        ];
+
+        for row in rows {
+            aggregator.push(ByteRecord::from(row));
+        }
+
+        let mut output = vec![];
+        aggregator.finish(&mut |record| {
+            output.push(StringRecord::from_byte_record(record).unwrap());
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(output, vec![
+            StringRecord::from(vec!["North", "3", "20", "10", "11", "2", "cid"]),
+            StringRecord::from(vec!["South", "1", "100", "100", "100", "1", "bob"]),
+        ]);
+    }
+    /// Aggregate rows of `region,amount` by region.
+    fn aggregate_by_region(
+        aggregates: Vec<(&str, Aggregate)>,
+        rows: Vec<Vec<&str>>,
+    ) -> Vec<StringRecord> {
+        let aggregates = aggregates.into_iter().map(
+            |(name, aggregate)| (name.to_string(), aggregate),
+        ).collect();
+
+        let mut aggregator = Aggregator::new(vec!["region".to_string()], aggregates);
+        aggregator.resolve(&StringRecord::from(vec!["region", "amount"])).unwrap();
+
+        for row in rows {
+            aggregator.push(ByteRecord::from(row));
+        }
+
+        let mut output = vec![];
+        aggregator.finish(&mut |record| {
+            output.push(StringRecord::from_byte_record(record).unwrap());
+            Ok(())
+        }).unwrap();
+
+        output
+    }
+
+    #[test]
+    fn test_decimal_sum_and_average() {
+        let output = aggregate_by_region(
+            vec![
+                ("total", aggregate(Function::Sum, Some("amount"), None)),
+                ("average", aggregate(Function::Avg, Some("amount"), None)),
+            ],
+            vec![
+                vec!["North", "0.1"],
+                vec!["North", "0.2"],
+                vec!["South", "10.00"],
+                vec!["South", "5"],
+            ],
+        );
+
+        assert_eq!(output, vec![
+            StringRecord::from(vec!["North", "0.3", "0.15"]),
+            StringRecord::from(vec!["South", "15.00", "7.50"]),
+        ]);
+    }
+
+    #[test]
+    fn test_min_max_without_type() {
+        let output = aggregate_by_region(
+            vec![
+                ("smallest", aggregate(Function::Min, Some("amount"), None)),
+                ("largest", aggregate(Function::Max, Some("amount"), None)),
+            ],
+            vec![
+                vec!["North", "9"],
+                vec!["North", "100"],
+                vec!["North", "-2.5"],
+                vec!["South", "9"],
+                vec!["South", "100"],
+                vec!["South", "n/a"],
+            ],
+        );
+
+        // Numbers while every value is one, text once one is not
+        assert_eq!(output, vec![
+            StringRecord::from(vec!["North", "-2.5", "100"]),
+            StringRecord::from(vec!["South", "100", "n/a"]),
+        ]);
+    }
+}
@@ -0,0 +1,31 @@
+use regex::RegexSet;
+
+use crate::compile::models::{Classify, MaybeSomeTransformation};
+use crate::compile::replace::compile_regex;
+use crate::transform::Transformation;
+
+
+/// Compile all the rules into one RegexSet, so that a value is matched against all of them
+/// in a single pass no matter how many rules there are.
+pub fn compile_classify(classify: &Classify) -> MaybeSomeTransformation {
+    let patterns: Vec<&str> = classify.rules.iter().map(
+        |rule| rule.pattern.as_str(),
+    ).collect();
+
+    // Check the patterns one by one to report which of them is broken
+    for pattern in patterns.iter() {
+        compile_regex(pattern)?;
+    }
+
+    let patterns = RegexSet::new(&patterns).map_err(
+        |err| format!("Cannot compile classification rules: {}", err),
+    )?;
+
+    Ok(Some(Transformation::Classify {
+        patterns,
+        values: classify.rules.iter().map(
+            |rule| rule.value.clone(),
+        ).collect(),
+        default: classify.default.clone(),
+    }))
+}
@@ -3,9 +3,9 @@ use crate::transform::Transformation;
 
 
 pub fn compile_date_with_multiple_formats(
-    formats: &Vec<String>,
+    formats: &[String],
 ) -> MaybeSomeTransformation {
     Ok(Some(Transformation::DateMultiple {
-        formats: formats.clone(),
+        formats: formats.to_vec(),
     }))
 }
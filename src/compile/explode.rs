@@ -0,0 +1,23 @@
+use crate::compile::models::ExplodeOptions;
+use crate::compile::split::compile_separator;
+use crate::transform::Explode;
+
+
+/// Returns the output headers (the column itself and, optionally, the part number) too.
+pub fn compile_explode(
+    column_name: &str,
+    explode: &ExplodeOptions,
+) -> Result<(Vec<String>, Explode), String> {
+    let separator = compile_separator(&explode.separator, &explode.pattern)?;
+
+    let mut headers = vec![column_name.to_string()];
+    if let Some(index_column) = &explode.index_column {
+        headers.push(index_column.clone());
+    }
+
+    Ok((headers, Explode {
+        separator,
+        on_empty: explode.on_empty,
+        with_index: explode.index_column.is_some(),
+    }))
+}
@@ -0,0 +1,36 @@
+use regex::Regex;
+
+use crate::compile::models::{Extract, MaybeSomeTransformation};
+use crate::compile::replace::compile_regex;
+use crate::transform::{CaptureGroup, Transformation};
+
+
+pub fn capture_group_exists(pattern: &Regex, group: &CaptureGroup) -> bool {
+    match group {
+        CaptureGroup::Index(index) => *index < pattern.captures_len(),
+        CaptureGroup::Name(name) => pattern.capture_names().any(
+            |capture_name| capture_name == Some(name.as_str()),
+        ),
+    }
+}
+
+
+/// Compile the pattern once and make sure the requested capture group exists in it.
+pub fn compile_extract(extract: &Extract) -> MaybeSomeTransformation {
+    let pattern = compile_regex(extract.pattern.as_str())?;
+
+    if !capture_group_exists(&pattern, &extract.group) {
+        return Err(format!(
+            "Capture group {:?} does not exist in regular expression {}.",
+            extract.group,
+            extract.pattern,
+        ));
+    }
+
+    Ok(Some(Transformation::Extract {
+        pattern,
+        group: extract.group.clone(),
+        join: extract.join.clone(),
+        on_no_match: extract.on_no_match,
+    }))
+}
@@ -0,0 +1,52 @@
+use crate::compile::extract::capture_group_exists;
+use crate::compile::models::{CapturesOutputs, SplitOutputs};
+use crate::compile::replace::compile_regex;
+use crate::compile::split::compile_separator;
+use crate::transform::{CaptureGroup, Fanout};
+
+
+/// Without explicit outputs, every named group becomes an output column of the same name.
+pub fn compile_captures(captures: &CapturesOutputs) -> Result<(Vec<String>, Fanout), String> {
+    let pattern = compile_regex(captures.pattern.as_str())?;
+
+    let outputs: Vec<(String, CaptureGroup)> = match &captures.outputs {
+        Some(outputs) => outputs.iter().map(
+            |(header, group)| (header.clone(), group.clone()),
+        ).collect(),
+
+        None => pattern.capture_names().flatten().map(
+            |name| (name.to_string(), CaptureGroup::Name(name.to_string())),
+        ).collect(),
+    };
+
+    if outputs.is_empty() {
+        return Err(format!(
+            "Regular expression {} has no named groups to produce output columns from.",
+            captures.pattern,
+        ));
+    }
+
+    for (header, group) in outputs.iter() {
+        if !capture_group_exists(&pattern, group) {
+            return Err(format!(
+                "Capture group {:?} for output column '{}' does not exist in regular expression {}.",
+                group, header, captures.pattern,
+            ));
+        }
+    }
+
+    let (headers, groups) = outputs.into_iter().unzip();
+
+    Ok((headers, Fanout::Captures { pattern, groups }))
+}
+
+
+pub fn compile_split_outputs(split: &SplitOutputs) -> Result<(Vec<String>, Fanout), String> {
+    let separator = compile_separator(&split.separator, &split.pattern)?;
+    let (headers, indices) = split.outputs.iter().map(
+        |(header, index)| (header.clone(), *index),
+    ).unzip();
+
+    Ok((headers, Fanout::Split { separator, indices }))
+}
+
@@ -0,0 +1,168 @@
+use chrono::NaiveDate;
+use csv::StringRecord;
+
+use crate::compile::headers::InputColumnIndexByName;
+use crate::compile::models::{Condition, ValueCondition};
+use crate::compile::replace::compile_regex;
+use crate::transform::{Comparison, Filter, Source, Test};
+
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+
+fn compile_source(
+    condition: &ValueCondition,
+    input_column_index_by_name: &InputColumnIndexByName,
+    output_headers: &StringRecord,
+) -> Result<Source, String> {
+    match (&condition.column, &condition.input) {
+        (Some(column), None) => output_headers.iter().position(
+            |header| header == column,
+        ).map(Source::Output).ok_or(format!(
+            "Filter refers to output column '{}' which does not exist.",
+            column,
+        )),
+
+        (None, Some(input)) => input_column_index_by_name.get(input)?.map(
+            Source::Input,
+        ).ok_or(format!(
+            "Filter refers to input column '{}' which does not exist.",
+            input,
+        )),
+
+        _ => Err(
+            "Filter condition needs exactly one of 'column' and 'input'.".to_string(),
+        ),
+    }
+}
+
+
+fn compile_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, DEFAULT_DATE_FORMAT).map_err(
+        |_err| format!("Filter date '{}' must be in YYYY-MM-DD format.", date),
+    )
+}
+
+
+/// All the tests given in one condition; several of them mean they all must pass.
+fn compile_tests(condition: &ValueCondition) -> Result<Vec<Test>, String> {
+    let mut tests = vec![];
+
+    if let Some(expected) = &condition.equals {
+        tests.push(Test::Equals(expected.clone()));
+    }
+
+    if let Some(pattern) = &condition.matches {
+        tests.push(Test::Matches(compile_regex(pattern)?));
+    }
+
+    if let Some(expected) = condition.empty {
+        tests.push(Test::Empty(expected));
+    }
+
+    let numeric_comparisons = [
+        (condition.gt, Comparison::Greater),
+        (condition.gte, Comparison::GreaterOrEqual),
+        (condition.lt, Comparison::Less),
+        (condition.lte, Comparison::LessOrEqual),
+    ];
+
+    for (maybe_number, comparison) in numeric_comparisons.iter() {
+        if let Some(number) = maybe_number {
+            tests.push(Test::Number(*comparison, *number));
+        }
+    }
+
+    let format = condition.date_format.clone().unwrap_or_else(
+        || DEFAULT_DATE_FORMAT.to_string(),
+    );
+
+    let date_comparisons = [
+        (&condition.after, Comparison::Greater),
+        (&condition.before, Comparison::Less),
+    ];
+
+    for (maybe_date, comparison) in date_comparisons.iter() {
+        if let Some(date) = maybe_date {
+            tests.push(Test::Date {
+                comparison: *comparison,
+                date: compile_date(date)?,
+                format: format.clone(),
+            });
+        }
+    }
+
+    Ok(tests)
+}
+
+
+/// Resolve column names of the condition tree to indices of the input or output record.
+pub fn compile_filter(
+    condition: &Condition,
+    input_column_index_by_name: &InputColumnIndexByName,
+    output_headers: &StringRecord,
+) -> Result<Filter, String> {
+    let compile_all = |conditions: &[Condition]| conditions.iter().map(
+        |condition| compile_filter(condition, input_column_index_by_name, output_headers),
+    ).collect::<Result<Vec<Filter>, String>>();
+
+    match condition {
+        Condition::All { all } => Ok(Filter::All(compile_all(all)?)),
+        Condition::Any { any } => Ok(Filter::Any(compile_all(any)?)),
+        Condition::Not { not } => Ok(Filter::Not(Box::new(compile_filter(
+            not,
+            input_column_index_by_name,
+            output_headers,
+        )?))),
+
+        Condition::Value(value_condition) => {
+            let source = compile_source(
+                value_condition,
+                input_column_index_by_name,
+                output_headers,
+            )?;
+
+            let mut tests = compile_tests(value_condition)?;
+
+            match tests.len() {
+                0 => Err(format!(
+                    "Filter condition on {:?} does not check anything.",
+                    source,
+                )),
+
+                1 => Ok(Filter::Test { source, test: tests.remove(0) }),
+
+                _ => Ok(Filter::All(tests.into_iter().map(
+                    |test| Filter::Test { source, test },
+                ).collect())),
+            }
+        },
+    }
+}
+
+
+/// Input columns the condition looks at. Names which do not resolve are skipped here;
+/// compiling the filter reports them.
+pub fn filter_input_columns(
+    condition: &Condition,
+    input_column_index_by_name: &InputColumnIndexByName,
+) -> Result<Vec<usize>, String> {
+    match condition {
+        Condition::All { all: conditions } | Condition::Any { any: conditions } => {
+            let mut columns = vec![];
+
+            for condition in conditions.iter() {
+                columns.extend(filter_input_columns(condition, input_column_index_by_name)?);
+            }
+
+            Ok(columns)
+        },
+
+        Condition::Not { not } => filter_input_columns(not, input_column_index_by_name),
+
+        Condition::Value(value_condition) => match &value_condition.input {
+            Some(input) => Ok(input_column_index_by_name.get(input)?.into_iter().collect()),
+            None => Ok(vec![]),
+        },
+    }
+}
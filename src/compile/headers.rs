@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::cmp::max;
+
+use serde::Deserialize;
+
+
+/// How the input column names in the config are matched against the input headers.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HeaderMatching {
+    #[default]
+    Exact,
+
+    /// Ignore case, spaces, punctuation and a byte order mark: `First Name` matches `first_name`.
+    Normalized,
+}
+
+
+/// What to do when an input column named in the config is not in the input.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingColumns {
+    /// Print a warning; the column is empty.
+    #[default]
+    Warn,
+
+    /// Stop, suggesting the headers closest to the missing name.
+    Error,
+
+    /// The column is silently empty.
+    Empty,
+}
+
+
+// Most headers suggested instead of a missing one.
+const MAX_SUGGESTIONS: usize = 3;
+
+
+/// Only letters and digits, lowercase. Drops the byte order mark, surrounding spaces,
+/// punctuation and underscores along with any other separators.
+pub fn normalize_header(header: &str) -> String {
+    header.chars().filter(
+        |character| character.is_alphanumeric(),
+    ).flat_map(char::to_lowercase).collect()
+}
+
+
+/// Positions of the input columns by their headers. An exact match always wins; the
+/// normalized one is tried only if there is none.
+#[derive(Default)]
+pub struct InputColumnIndexByName {
+    matching: HeaderMatching,
+    exact: BTreeMap<String, usize>,
+    normalized: BTreeMap<String, Vec<(String, usize)>>,
+}
+
+
+impl InputColumnIndexByName {
+    pub fn with_matching(matching: HeaderMatching) -> InputColumnIndexByName {
+        InputColumnIndexByName { matching, ..InputColumnIndexByName::default() }
+    }
+
+    pub fn insert(&mut self, header: String, index: usize) {
+        if let HeaderMatching::Normalized = self.matching {
+            self.normalized.entry(normalize_header(&header)).or_default().push(
+                (header.clone(), index),
+            );
+        }
+
+        self.exact.insert(header, index);
+    }
+
+    /// Fails if the name matches several headers after normalization.
+    pub fn get(&self, name: &str) -> Result<Option<usize>, String> {
+        if let Some(index) = self.exact.get(name) {
+            return Ok(Some(*index));
+        }
+
+        match self.normalized.get(&normalize_header(name)).map(Vec::as_slice) {
+            None | Some([]) => Ok(None),
+            Some([(_, index)]) => Ok(Some(*index)),
+
+            Some(candidates) => Err(format!(
+                "Input column '{}' is ambiguous: it matches {}.",
+                name,
+                candidates.iter().map(
+                    |(header, _)| format!("'{}'", header),
+                ).collect::<Vec<_>>().join(", "),
+            )),
+        }
+    }
+
+    /// Headers within a small edit distance of the name, closest first. Case is ignored, and
+    /// so is everything normalized matching ignores if it is on; swapping two neighbouring
+    /// characters counts as one edit. A short name needs a close match: no header is
+    /// suggested if it takes as many edits as the name has characters.
+    pub fn suggestions(&self, name: &str) -> Vec<&str> {
+        let comparable = |text: &str| match self.matching {
+            HeaderMatching::Exact => text.to_lowercase(),
+            HeaderMatching::Normalized => normalize_header(text),
+        };
+
+        let name = comparable(name);
+        let length = name.chars().count();
+        let limit = max(1, length / 3);
+
+        let mut candidates: Vec<(usize, &str)> = self.exact.keys().map(
+            |header| (strsim::damerau_levenshtein(&name, &comparable(header)), header.as_str()),
+        ).filter(
+            |(distance, _)| *distance <= limit && *distance < length,
+        ).collect();
+
+        candidates.sort();
+
+        candidates.into_iter().take(MAX_SUGGESTIONS).map(|(_, header)| header).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_by_name(headers: &[&str]) -> InputColumnIndexByName {
+        let mut index_by_name = InputColumnIndexByName::with_matching(HeaderMatching::Normalized);
+
+        for (index, header) in headers.iter().enumerate() {
+            index_by_name.insert(header.to_string(), index);
+        }
+
+        index_by_name
+    }
+
+    #[test]
+    fn test_normalized_matching() {
+        let index_by_name = index_by_name(&["\u{feff}ID", " First Name ", "e-mail"]);
+
+        assert_eq!(index_by_name.get("id"), Ok(Some(0)));
+        assert_eq!(index_by_name.get("first_name"), Ok(Some(1)));
+        assert_eq!(index_by_name.get("Email"), Ok(Some(2)));
+        assert_eq!(index_by_name.get("phone"), Ok(None));
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let index_by_name = index_by_name(&["Email", "Name", "Emails", "Phone"]);
+
+        assert_eq!(index_by_name.suggestions("emial"), vec!["Email"]);
+        assert_eq!(index_by_name.suggestions("e_mials"), vec!["Emails", "Email"]);
+        assert!(index_by_name.suggestions("address").is_empty());
+    }
+
+    #[test]
+    fn test_suggestions_for_short_names() {
+        let mut index_by_name = InputColumnIndexByName::default();
+
+        for (index, header) in ["ab", "xy", "name"].iter().enumerate() {
+            index_by_name.insert(header.to_string(), index);
+        }
+
+        assert!(index_by_name.suggestions("id").is_empty());
+        assert_eq!(index_by_name.suggestions("nme"), vec!["name"]);
+    }
+
+    #[test]
+    fn test_exact_match_wins_over_ambiguity() {
+        let index_by_name = index_by_name(&["First Name", "first_name"]);
+
+        assert_eq!(index_by_name.get("first_name"), Ok(Some(1)));
+        assert!(index_by_name.get("FIRST NAME").is_err());
+    }
+}
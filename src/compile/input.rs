@@ -1,40 +1,82 @@
 use crate::transform::Transformation;
-use crate::compile::models::{InputColumnIndexByName, MaybeSomeTransformation};
+use crate::compile::headers::{InputColumnIndexByName, MissingColumns};
+use crate::compile::models::MaybeSomeTransformation;
 
 
 /// Compiles the specified input column name to a Transformation with the index of the said column.
 pub fn compile_singular_input(
-    input_column_name: &String,
+    input_column_name: &str,
     input_column_index_by_name: &InputColumnIndexByName,
 ) -> MaybeSomeTransformation {
     let input_column_index = input_column_index_by_name.get(
         input_column_name,
-    );
+    )?;
 
-    if input_column_index.is_none() {
-        // FIXME this should not be here
-        eprintln!("Warning: input column {} not found.", input_column_name);
-    }
-
-    Ok(input_column_index.map(
-        |index| Transformation::Input(index.clone()),
-    ))
+    Ok(input_column_index.map(Transformation::Input))
 }
 
 
 /// Provided a list of several input column names, find the first column which actually
 /// exists in the input stream, and use it. Useful to coerce multiple schemas to one.
 pub fn compile_multiple_input(
-    input_column_names: &Vec<String>,
+    input_column_names: &[String],
     input_column_index_by_name: &InputColumnIndexByName,
 ) -> MaybeSomeTransformation {
-    let maybe_input_column_index: Option<&usize> = input_column_names.iter().map(
-        |column_name| input_column_index_by_name.get(column_name),
-    ).flatten().next();
+    for column_name in input_column_names.iter() {
+        if let Some(index) = input_column_index_by_name.get(column_name)? {
+            return Ok(Some(Transformation::Input(index)));
+        }
+    }
+
+    Ok(None)
+}
+
 
-    Ok(maybe_input_column_index.map(
-        |index| Transformation::Input(index.clone()),
-    ))
+/// Apply the missing columns policy if none of the input columns was found.
+pub fn check_missing_input(
+    transformation: Option<Transformation>,
+    input_column_names: &[String],
+    input_column_index_by_name: &InputColumnIndexByName,
+    missing_columns: MissingColumns,
+) -> MaybeSomeTransformation {
+    if transformation.is_some() {
+        return Ok(transformation);
+    }
+
+    let names = input_column_names.join(", ");
+
+    match missing_columns {
+        MissingColumns::Empty => Ok(None),
+
+        MissingColumns::Warn => {
+            eprintln!("Warning: input column {} not found.", names);
+            Ok(None)
+        },
+
+        MissingColumns::Error => {
+            let mut suggestions: Vec<String> = vec![];
+            for name in input_column_names.iter() {
+                for suggestion in input_column_index_by_name.suggestions(name) {
+                    let suggestion = format!("'{}'", suggestion);
+                    if !suggestions.contains(&suggestion) {
+                        suggestions.push(suggestion);
+                    }
+                }
+            }
+
+            let hint = if suggestions.is_empty() {
+                String::new()
+            } else {
+                format!(" Did you mean {}?", suggestions.join(" or "))
+            };
+
+            let quoted_names: Vec<String> = input_column_names.iter().map(
+                |name| format!("'{}'", name),
+            ).collect();
+
+            Err(format!("Input column {} not found.{}", quoted_names.join(", "), hint))
+        },
+    }
 }
 
 
@@ -42,13 +84,14 @@ pub fn compile_multiple_input(
 mod tests {
     use super::*;
 
+    #[test]
     fn test_compile_multiple_input_first() {
         let names = vec![
             "Date".to_string(),
             "date".to_string(),
         ];
 
-        let mut indices = InputColumnIndexByName::new();
+        let mut indices = InputColumnIndexByName::default();
 
         indices.insert("date".to_string(), 2);
         indices.insert("Date".to_string(), 1);
@@ -58,14 +101,11 @@ mod tests {
             &indices,
         ).unwrap().unwrap();
 
-        // FIXME
-        // assert_eq!(
-        //     transformation,
-        //     Transformation::Input(1),
-        // )
+        assert!(matches!(transformation, Transformation::Input(1)));
     }
 
 
+    #[test]
     fn test_compile_multiple_input_second() {
         let names = vec![
             "Date".to_string(),
@@ -73,7 +113,7 @@ mod tests {
             "Transaction Date".to_string(),
         ];
 
-        let mut indices = InputColumnIndexByName::new();
+        let mut indices = InputColumnIndexByName::default();
 
         indices.insert("Transaction Date".to_string(), 5);
         indices.insert("Event Date".to_string(), 1);
@@ -83,12 +123,10 @@ mod tests {
             &indices,
         ).unwrap().unwrap();
 
-        // assert_eq!(
-        //     transformation,
-        //     Transformation::Input(5),
-        // )
+        assert!(matches!(transformation, Transformation::Input(5)));
     }
 
+    #[test]
     fn test_compile_multiple_input_empty() {
         let names = vec![
             "Date".to_string(),
@@ -96,7 +134,7 @@ mod tests {
             "Transaction Date".to_string(),
         ];
 
-        let mut indices = InputColumnIndexByName::new();
+        let mut indices = InputColumnIndexByName::default();
 
         indices.insert("Happening Date".to_string(), 5);
         indices.insert("Event Date".to_string(), 1);
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::compile::headers::InputColumnIndexByName;
+use crate::compile::models::{Lookup, MaybeSomeTransformation};
+use crate::transform::{LookupTable, Transformation};
+
+
+/// Reference tables loaded once per run and reused for every input file and output.
+/// Columns which look up the same file by the same key share one table.
+pub struct LookupTables {
+    base_directory: PathBuf,
+    tables: HashMap<(PathBuf, Vec<String>), Arc<LookupTable>>,
+}
+
+
+impl LookupTables {
+    pub fn new(base_directory: &Path) -> LookupTables {
+        LookupTables {
+            base_directory: base_directory.to_path_buf(),
+            tables: HashMap::new(),
+        }
+    }
+
+    fn get_or_load(
+        &mut self,
+        file: &str,
+        key: Vec<String>,
+    ) -> Result<Arc<LookupTable>, String> {
+        let path = self.base_directory.join(file);
+        let cache_key = (path, key);
+
+        if let Some(table) = self.tables.get(&cache_key) {
+            return Ok(table.clone());
+        }
+
+        let table = Arc::new(load_lookup_table(&cache_key.0, &cache_key.1)?);
+        self.tables.insert(cache_key, table.clone());
+
+        Ok(table)
+    }
+}
+
+
+fn load_lookup_table(path: &Path, key: &[String]) -> Result<LookupTable, String> {
+    let name = path.display().to_string();
+
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path).map_err(
+            |err| format!("Cannot open lookup table {}: {}", name, err),
+        )?;
+
+    let headers = reader.headers().map_err(
+        |err| format!("Cannot read headers of lookup table {}: {}", name, err),
+    )?.clone();
+
+    let key_columns: Vec<usize> = key.iter().map(
+        |column_name| headers.iter().position(|header| header == column_name).ok_or(format!(
+            "Key column '{}' not found in lookup table {}.",
+            column_name, name,
+        )),
+    ).collect::<Result<_, String>>()?;
+
+    let rows: Vec<StringRecord> = reader.records().collect::<Result<_, _>>().map_err(
+        |err| format!("Cannot read lookup table {}: {}", name, err),
+    )?;
+
+    Ok(LookupTable::new(name, headers, rows, &key_columns))
+}
+
+
+/// Load (or reuse) the reference table and resolve all column names to indices.
+pub fn compile_lookup(
+    lookup: &Lookup,
+    input_column_index_by_name: &InputColumnIndexByName,
+    lookup_tables: &mut LookupTables,
+) -> MaybeSomeTransformation {
+    let key = lookup.key.to_vec();
+    let table = lookup_tables.get_or_load(&lookup.file, key.clone())?;
+
+    let column = table.column_index(&lookup.return_column).ok_or(format!(
+        "Return column '{}' not found in lookup table {}.",
+        lookup.return_column, lookup.file,
+    ))?;
+
+    let input = match &lookup.input {
+        None => None,
+        Some(names) => Some(names.to_vec().iter().map(
+            |name| input_column_index_by_name.get(name)?.ok_or(format!(
+                "Input column '{}' for lookup in {} not found.",
+                name, lookup.file,
+            )),
+        ).collect::<Result<Vec<usize>, String>>()?),
+    };
+
+    if let Some(indices) = &input {
+        if indices.len() != key.len() {
+            return Err(format!(
+                "Lookup in {} has {} key column(s) but {} input column(s).",
+                lookup.file, key.len(), indices.len(),
+            ));
+        }
+    }
+
+    Ok(Some(Transformation::Lookup {
+        table,
+        column,
+        input,
+        on_miss: lookup.on_miss,
+    }))
+}
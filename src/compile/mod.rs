@@ -1,38 +1,118 @@
-use std::collections::BTreeMap;
 use std::fs;
+use std::path::Path;
 
 use csv::StringRecord;
 
 use crate::compile::date::compile_date_with_multiple_formats;
-use crate::compile::input::{compile_multiple_input, compile_singular_input};
-use crate::compile::models::{Column, Expression, InputColumnIndexByName, MaybeSomeTransformation};
-pub use crate::compile::models::Config;
-use crate::compile::replace::compile_replace_regex;
+use crate::compile::input::{check_missing_input, compile_multiple_input, compile_singular_input};
+use crate::compile::lookup::compile_lookup;
+use crate::compile::extract::compile_extract;
+use crate::compile::classify::compile_classify;
+use crate::compile::split::compile_split;
+use crate::compile::fanout::{compile_captures, compile_split_outputs};
+use crate::compile::explode::compile_explode;
+use crate::compile::filter::{compile_filter, filter_input_columns};
+use crate::compile::unpivot::compile_unpivot;
+use crate::compile::passthrough::compile_passthrough;
+use crate::compile::template::{expand_column_templates, ExpandedColumn};
+use crate::compile::headers::{HeaderMatching, InputColumnIndexByName, MissingColumns};
+use crate::compile::state::{
+    compile_change_flag,
+    compile_fill_down,
+    compile_lag,
+    compile_row_number_by,
+    compile_running_sum,
+    StateSlots,
+};
+use crate::compile::models::{
+    Column,
+    DetailedColumn,
+    Expression,
+    FillDown,
+    Passthrough,
+    PassthroughOptions,
+    PassthroughPosition,
+    Window,
+    MaybeSomeTransformation,
+};
+pub use crate::compile::lookup::LookupTables;
+pub use crate::compile::models::{Config, InputOptions, OutputSpec, Sources};
+pub use crate::compile::replace::compile_regex;
+use crate::compile::replace::{compile_replace, compile_replace_regex};
 use crate::options::Variables;
-use crate::transform::{Transformation, Transformer};
+use crate::transform::{ColumnChain, ColumnOutput, Transformation, Transformer};
 use crate::worker::MaybeTransformationsChain;
 
 mod input;
 mod replace;
 mod models;
 mod date;
+mod lookup;
+mod extract;
+mod classify;
+mod split;
+mod fanout;
+mod explode;
+mod filter;
+mod unpivot;
+mod passthrough;
+mod template;
+mod headers;
+mod state;
 
 
 /// Load the YAML configuration file content into memory and parse it
 pub fn parse_config_from_file(path: &str) -> Result<Config, String> {
-    let content = fs::read_to_string(&path).map_err(
+    let content = fs::read_to_string(path).map_err(
         |err| format!("Can't open configuration file: {:?}", err),
     )?;
 
-    serde_yaml::from_str(&content).map_err(
+    let mut config: Config = serde_yaml::from_str(&content).map_err(
         |err| format!("Configuration file could not be parsed. Reason: {:?}", err)
-    )
+    )?;
+
+    config.base_directory = Path::new(path).parent().map(
+        |directory| directory.to_path_buf(),
+    ).unwrap_or_default();
+
+    // Parsed separately rather than flattened into the config, which would lose the
+    // positions of errors in it
+    config.output = serde_yaml::from_str(&content).map_err(
+        |err| format!("Configuration file could not be parsed. Reason: {:?}", err)
+    )?;
+
+    if config.outputs.is_some() && !config.output.columns.is_empty() {
+        return Err(
+            "Columns can be given either at the top level or under 'outputs', but not both.".to_string(),
+        );
+    }
+
+    config.output.merge_skip_if();
+    if let Some(outputs) = &mut config.outputs {
+        outputs.iter_mut().for_each(|(_, spec)| spec.merge_skip_if());
+    }
+
+    if config.sources.is_some() && config.input != InputOptions::default() {
+        return Err(
+            "Input options apply to the standard input and the input files, not to 'sources'.".to_string(),
+        );
+    }
+
+    if config.input.skip_footer_lines > 0 && config.input.footer_pattern.is_some() {
+        return Err(
+            "The input footer is found either by 'skip_footer_lines' or by 'footer_pattern', but not both.".to_string(),
+        );
+    }
+
+    Ok(config)
 }
 
 
-fn get_input_columns_index_map(headers: &StringRecord) -> BTreeMap<String, usize> {
-    // FIXME awful function, I do not know the proper method yet
-    let mut mapping = BTreeMap::new();
+fn get_input_columns_index_map(
+    headers: &StringRecord,
+    matching: HeaderMatching,
+) -> InputColumnIndexByName {
+    let mut mapping = InputColumnIndexByName::with_matching(matching);
 
     for (index, value) in headers.iter().enumerate() {
         mapping.insert(String::from(value), index);
@@ -43,12 +123,22 @@ fn get_input_columns_index_map(headers: &StringRecord) -> BTreeMap<String, usize
 
 
 fn transformation_without_parameters(
-    transformation_name: &String,
+    transformation_name: &str,
 ) -> MaybeSomeTransformation {
-    match transformation_name.as_str() {
+    match transformation_name {
         "uppercase" => Ok(Some(Transformation::Uppercase)),
         "lowercase" => Ok(Some(Transformation::Lowercase)),
+        "title_case" => compile_title_case(&[]),
+        "sentence_case" => Ok(Some(Transformation::SentenceCase)),
+        "snake_case" => Ok(Some(Transformation::SnakeCase)),
+        "kebab_case" => Ok(Some(Transformation::KebabCase)),
+        "camel_case" => Ok(Some(Transformation::CamelCase)),
         "line-number" => Ok(Some(Transformation::LineNumber)),
+        "strip" => Ok(Some(Transformation::Strip)),
+        "collapse_whitespace" => Ok(Some(Transformation::CollapseWhitespace)),
+        "remove_accents" => Ok(Some(Transformation::RemoveAccents)),
+        "remove_control_chars" => Ok(Some(Transformation::RemoveControlChars)),
+        "replace_nbsp" => Ok(Some(Transformation::ReplaceNbsp)),
         _ => Err(format!(
             "Transformation '{}' is not supported.",
             transformation_name,
@@ -57,15 +147,23 @@ fn transformation_without_parameters(
 }
 
 
+/// Index title case exceptions by their lowercase spelling.
+fn compile_title_case(exceptions: &[String]) -> MaybeSomeTransformation {
+    Ok(Some(Transformation::TitleCase {
+        exceptions: exceptions.iter().map(
+            |exception| (exception.to_lowercase(), exception.clone()),
+        ).collect(),
+    }))
+}
+
+
 fn variable_transformation(
-    name: &String,
+    name: &str,
     variables: &Variables,
 ) -> MaybeSomeTransformation {
     let value = variables.get(
         name,
-    ).map(
-        |value| value.clone(),
-    ).unwrap_or(
+    ).cloned().unwrap_or(
         "".to_string(),
     );
 
@@ -73,8 +171,8 @@ fn variable_transformation(
 }
 
 
-fn from_transformation(name: &String) -> MaybeSomeTransformation {
-    Ok(Some(Transformation::From { from: name.clone() }))
+fn from_transformation(name: &str) -> MaybeSomeTransformation {
+    Ok(Some(Transformation::From { from: name.to_string() }))
 }
 
 /// Create a specific date transformation based on the date format.
@@ -82,40 +180,65 @@ fn from_transformation(name: &String) -> MaybeSomeTransformation {
 /// If format is "excel-ordinal", we will use particular algorithm for Excel ordinal dates;
 /// otherwise, we will create a generic date transformation from normal formats. Thus, this will be
 /// a compile time decision and we will not have to compare the format with a constant in runtime.
-fn date_transformation(format: &String) -> MaybeSomeTransformation {
-    Ok(Some(match format.as_str() {
+fn date_transformation(format: &str) -> MaybeSomeTransformation {
+    Ok(Some(match format {
         "excel-ordinal" => Transformation::ExcelOrdinalDate,
-        _ => Transformation::Date { format: format.clone() }
+        _ => Transformation::Date { format: format.to_string() }
     }))
 }
 
 
 fn compile_expression(
     step: &Expression,
-    input_column_index_by_name: &BTreeMap<String, usize>,
+    input_column_index_by_name: &InputColumnIndexByName,
+    missing_columns: MissingColumns,
     variables: &Variables,
+    lookup_tables: &mut LookupTables,
+    slots: &mut StateSlots,
 ) -> MaybeSomeTransformation {
     match step {
-        Expression::Input {input} => compile_singular_input(
-            input,
+        Expression::Input {input} => check_missing_input(
+            compile_singular_input(input, input_column_index_by_name)?,
+            std::slice::from_ref(input),
             input_column_index_by_name,
+            missing_columns,
         ),
 
-        Expression::MultipleInput { input } => compile_multiple_input(
+        Expression::MultipleInput { input } => check_missing_input(
+            compile_multiple_input(input, input_column_index_by_name)?,
             input,
             input_column_index_by_name,
+            missing_columns,
         ),
 
         Expression::Trim {trim} => Ok(Some(Transformation::Slice { start: 0, end: *trim })),
 
-        Expression::Replace { replace } => Ok(Some(
-            Transformation::Replace { replace: replace.clone() }
-        )),
+        Expression::Split { split } => compile_split(split),
+
+        Expression::Pad { pad } => Ok(Some(Transformation::Pad {
+            width: pad.width,
+            side: pad.side,
+            fill: pad.fill,
+        })),
+
+        Expression::Truncate { truncate } => Ok(Some(Transformation::Truncate {
+            width: truncate.width,
+            on_overflow: truncate.on_overflow,
+        })),
+
+        Expression::Replace { replace, sequential } => compile_replace(
+            replace,
+            *sequential,
+        ),
 
         Expression::ReplaceRegex { replace_regex } => compile_replace_regex(
             replace_regex,
         ),
 
+        Expression::Extract { extract } => compile_extract(extract),
+
+        Expression::Classify { classify } => compile_classify(classify),
+
         Expression::Variable { var: variable } => variable_transformation(
             variable,
             variables,
@@ -127,9 +250,66 @@ fn compile_expression(
 
         Expression::From { from } => from_transformation(from),
 
+        Expression::Normalize { normalize: form } => Ok(Some(
+            Transformation::Normalize { form: *form }
+        )),
+
+        Expression::TitleCase { title_case } => compile_title_case(&title_case.exceptions),
+
         Expression::Date { date } => date_transformation(date),
         Expression::MultipleDate { date } => compile_date_with_multiple_formats(date),
 
+        Expression::Lookup { lookup } => compile_lookup(
+            lookup,
+            input_column_index_by_name,
+            lookup_tables,
+        ),
+
+        Expression::FillDown { fill_down } => compile_fill_down(
+            fill_down,
+            input_column_index_by_name,
+            slots,
+        ),
+
+        Expression::Lag { lag } => compile_lag(lag, input_column_index_by_name, slots),
+
+        Expression::RunningSum { running_sum } => compile_running_sum(
+            running_sum,
+            input_column_index_by_name,
+            slots,
+        ),
+
+        Expression::RowNumberBy { row_number_by } => compile_row_number_by(
+            row_number_by,
+            input_column_index_by_name,
+            slots,
+        ),
+
+        Expression::ChangeFlag { change_flag } => compile_change_flag(
+            change_flag,
+            input_column_index_by_name,
+            slots,
+        ),
+
+        // Stateful, so these are not plain transformations without parameters
+        Expression::Operation(value) if value == "fill_down" => compile_fill_down(
+            &FillDown::default(),
+            input_column_index_by_name,
+            slots,
+        ),
+
+        Expression::Operation(value) if value == "running_sum" => compile_running_sum(
+            &Window::default(),
+            input_column_index_by_name,
+            slots,
+        ),
+
+        Expression::Operation(value) if value == "change_flag" => compile_change_flag(
+            &Window::default(),
+            input_column_index_by_name,
+            slots,
+        ),
+
         Expression::Operation(value) => transformation_without_parameters(
             value,
         )
@@ -138,18 +318,24 @@ fn compile_expression(
 
 
 fn shorthand_input_to_transformations_chain(
-    input_column_name: &String,
+    input_column_name: &str,
     input_column_index_by_name: &InputColumnIndexByName,
+    missing_columns: MissingColumns,
     variables: &Variables,
+    lookup_tables: &mut LookupTables,
+    slots: &mut StateSlots,
 ) -> MaybeTransformationsChain {
     let step = Expression::Input {
-        input: input_column_name.clone(),
+        input: input_column_name.to_string(),
     };
 
     let maybe_some_transformation = compile_expression(
         &step,
         input_column_index_by_name,
+        missing_columns,
         variables,
+        lookup_tables,
+        slots,
     );
 
     maybe_some_transformation.map(
@@ -162,15 +348,21 @@ fn shorthand_input_to_transformations_chain(
 
 
 fn expressions_to_transformations_chain(
-    expressions: &Vec<Expression>,
+    expressions: &[Expression],
     input_column_index_by_name: &InputColumnIndexByName,
+    missing_columns: MissingColumns,
     variables: &Variables,
+    lookup_tables: &mut LookupTables,
+    slots: &mut StateSlots,
 ) -> MaybeTransformationsChain {
     let mapped_steps = expressions.iter().map(
         |step| compile_expression(
             step,
-            &input_column_index_by_name,
-            &variables,
+            input_column_index_by_name,
+            missing_columns,
+            variables,
+            lookup_tables,
+            slots,
         ),
     );
 
@@ -186,43 +378,191 @@ fn expressions_to_transformations_chain(
 fn column_to_transformations_chain(
     column: &Column,
     input_column_index_by_name: &InputColumnIndexByName,
+    missing_columns: MissingColumns,
     variables: &Variables,
+    lookup_tables: &mut LookupTables,
+    slots: &mut StateSlots,
 ) -> MaybeTransformationsChain {
     match column {
         Column::Input(input_column_name) => shorthand_input_to_transformations_chain(
             input_column_name,
             input_column_index_by_name,
+            missing_columns,
             variables,
+            lookup_tables,
+            slots,
         ),
 
         Column::Expressions(steps) => expressions_to_transformations_chain(
             steps,
             input_column_index_by_name,
+            missing_columns,
             variables,
+            lookup_tables,
+            slots,
+        ),
+
+        Column::Detailed(detailed_column) => expressions_to_transformations_chain(
+            &detailed_column.steps,
+            input_column_index_by_name,
+            detailed_column.missing_columns.unwrap_or(missing_columns),
+            variables,
+            lookup_tables,
+            slots,
+        ),
+    }
+}
+
+
+/// Decide where the value of a detailed column goes, and under which header(s).
+fn compile_column_output(
+    column_name: &str,
+    column: &DetailedColumn,
+) -> Result<(Vec<String>, ColumnOutput), String> {
+    match (&column.captures, &column.split, &column.explode) {
+        (None, None, None) => Ok((vec![column_name.to_string()], ColumnOutput::Single)),
+
+        (Some(captures), None, None) => compile_captures(captures).map(
+            |(headers, fanout)| (headers, ColumnOutput::Fanout(fanout)),
+        ),
+
+        (None, Some(split), None) => compile_split_outputs(split).map(
+            |(headers, fanout)| (headers, ColumnOutput::Fanout(fanout)),
+        ),
+
+        (None, None, Some(explode)) => compile_explode(column_name, explode).map(
+            |(headers, explode)| (headers, ColumnOutput::Explode(explode)),
         ),
+
+        _ => Err(format!(
+            "Column '{}' can have only one of 'captures', 'split' and 'explode'.",
+            column_name,
+        )),
     }
 }
 
 
 pub fn create_transformer(
     config: &Config,
+    spec: &OutputSpec,
     headers: &StringRecord,
     variables: &Variables,
+    lookup_tables: &mut LookupTables,
 ) -> Result<Transformer, String> {
-    let input_columns_index_by_name = get_input_columns_index_map(headers);
+    let (input_headers, unpivot) = match &config.unpivot {
+        Some(unpivot) => {
+            let (input_headers, unpivot) = compile_unpivot(unpivot, headers)?;
+            (input_headers, Some(unpivot))
+        },
+        None => (headers.clone(), None),
+    };
+
+    let input_columns_index_by_name = get_input_columns_index_map(
+        &input_headers,
+        config.header_matching,
+    );
+    let mut slots = StateSlots::default();
+
+    let mut output_headers: Vec<String> = vec![];
+    let mut columns: Vec<ColumnChain> = vec![];
+    let mut exploded_column_name: Option<String> = None;
 
-    let maybe_columns: Result<Vec<Vec<Transformation>>, String> = config.columns.values().map(
-        |column| column_to_transformations_chain(
+    for ExpandedColumn { name: column_name, column, input } in expand_column_templates(
+        &spec.columns,
+        &input_headers,
+    )? {
+        let mut transformations = column_to_transformations_chain(
             column,
             &input_columns_index_by_name,
+            config.missing_columns,
             variables,
-        ),
-    ).collect();
+            lookup_tables,
+            &mut slots,
+        )?;
+
+        if let Some(index) = input {
+            transformations.insert(0, Transformation::Input(index));
+        }
+
+        let (headers, output) = match column {
+            Column::Detailed(detailed_column) => compile_column_output(
+                &column_name,
+                detailed_column,
+            )?,
+
+            _ => (vec![column_name.clone()], ColumnOutput::Single),
+        };
+
+        if let ColumnOutput::Explode(_) = output {
+            if let Some(exploded_column_name) = exploded_column_name {
+                return Err(format!(
+                    "Cannot explode both '{}' and '{}': only one column can be exploded.",
+                    exploded_column_name, column_name,
+                ));
+            }
+
+            exploded_column_name = Some(column_name);
+        }
+
+        output_headers.extend(headers);
+        columns.push(ColumnChain { transformations, output });
+    }
+
+    let default_passthrough = PassthroughOptions::default();
+    let passthrough = match &spec.passthrough {
+        Some(Passthrough::Enabled(true)) => Some(&default_passthrough),
+        Some(Passthrough::Detailed(options)) => Some(options),
+        Some(Passthrough::Enabled(false)) | None => None,
+    };
 
-    let headers = config.columns.keys().collect();
+    if let Some(passthrough) = passthrough {
+        let mut consumed: Vec<usize> = columns.iter().flat_map(
+            |column| column.transformations.iter(),
+        ).flat_map(
+            Transformation::input_columns,
+        ).collect();
+
+        if let Some(condition) = &spec.filter {
+            consumed.extend(filter_input_columns(condition, &input_columns_index_by_name)?);
+        }
+
+        let (mut passthrough_headers, mut passthrough_columns) = compile_passthrough(
+            passthrough,
+            &input_headers,
+            &consumed,
+            &output_headers,
+        )?;
+
+        match passthrough.position {
+            PassthroughPosition::Last => {
+                output_headers.append(&mut passthrough_headers);
+                columns.append(&mut passthrough_columns);
+            },
+
+            PassthroughPosition::First => {
+                passthrough_headers.append(&mut output_headers);
+                passthrough_columns.append(&mut columns);
+                output_headers = passthrough_headers;
+                columns = passthrough_columns;
+            },
+        }
+    }
+
+    let headers = StringRecord::from(output_headers);
+
+    let filter = match &spec.filter {
+        Some(condition) => Some(compile_filter(
+            condition,
+            &input_columns_index_by_name,
+            &headers,
+        )?),
+        None => None,
+    };
 
     Ok(Transformer {
+        unpivot,
         headers,
-        columns: maybe_columns?,
+        columns,
+        filter,
     })
 }
@@ -1,10 +1,13 @@
 use serde::Deserialize;
-use std::collections::BTreeMap;
-use crate::transform::Transformation;
+use std::path::PathBuf;
+use crate::aggregate::Aggregate;
+use crate::compile::headers::{HeaderMatching, MissingColumns};
+use crate::dedupe::Keep;
+use crate::join::{BuildSide, JoinType};
+use crate::sort::SortColumn;
+use crate::transform::{CaptureGroup, EmptyParts, MissPolicy, NormalizationForm, Overflow, PadSide, Transformation};
 use linked_hash_map::LinkedHashMap;
 
-pub type InputColumnIndexByName = BTreeMap<String, usize>;
-
 pub type MaybeSomeTransformation = Result<Option<Transformation>, String>;
 
 
@@ -18,26 +21,265 @@ pub struct ReplaceRegex {
 }
 
 
+/// A single column name or a list of them.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ColumnNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+
+impl ColumnNames {
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            ColumnNames::One(name) => vec![name.clone()],
+            ColumnNames::Many(names) => names.clone(),
+        }
+    }
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct Lookup {
+    /// Reference CSV file, relative to the configuration file.
+    pub file: String,
+
+    /// Key column(s) of the reference file.
+    pub key: ColumnNames,
+
+    /// Input column(s) to build the key from. If omitted, the current value is the key.
+    pub input: Option<ColumnNames>,
+
+    /// Reference file column to return.
+    #[serde(rename = "return")]
+    pub return_column: String,
+
+    #[serde(default)]
+    pub on_miss: MissPolicy,
+}
+
+
+/// Options of `fill_down`; the plain `fill_down` operation uses the defaults.
+#[derive(Debug, Deserialize, Default)]
+pub struct FillDown {
+    /// Input column(s) which start a new group when their values change; nothing is
+    /// filled down from one group into the next.
+    pub reset_on: Option<ColumnNames>,
+}
+
+
+/// Value of an input column from an earlier row.
+#[derive(Debug, Deserialize)]
+pub struct Lag {
+    pub column: String,
+
+    /// How many rows back to look.
+    #[serde(default = "default_offset")]
+    pub offset: usize,
+
+    /// Input column(s) splitting the rows into groups; only rows of the same group count.
+    pub by: Option<ColumnNames>,
+}
+
+
+fn default_offset() -> usize {
+    1
+}
+
+
+/// Options of `running_sum` and `change_flag`; the plain operations keep one state for all rows.
+#[derive(Debug, Deserialize, Default)]
+pub struct Window {
+    /// Input column(s) splitting the rows into groups, each with a state of its own.
+    pub by: Option<ColumnNames>,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct Extract {
+    pub pattern: String,
+
+    /// Number or name of the capture group; the whole match by default.
+    #[serde(default)]
+    pub group: CaptureGroup,
+
+    /// If given, all matches are extracted and joined with this separator.
+    pub join: Option<String>,
+
+    #[serde(default)]
+    pub on_no_match: MissPolicy,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct ClassifyRule {
+    pub pattern: String,
+    pub value: String,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct Classify {
+    /// Rules are tried in order; the first one that matches wins.
+    pub rules: Vec<ClassifyRule>,
+
+    /// Value to use if no rule matches. Empty if not given.
+    pub default: Option<String>,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct TitleCase {
+    /// Words to spell exactly as given, like "McDonald" or "O'Neil".
+    #[serde(default)]
+    pub exceptions: Vec<String>,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct Pad {
+    pub width: usize,
+
+    #[serde(default)]
+    pub side: PadSide,
+
+    #[serde(default = "default_fill", rename = "char")]
+    pub fill: char,
+}
+
+
+fn default_fill() -> char {
+    ' '
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct Truncate {
+    pub width: usize,
+
+    #[serde(default)]
+    pub on_overflow: Overflow,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct Split {
+    /// Literal separator.
+    pub separator: Option<String>,
+
+    /// Regular expression to split by, instead of a literal separator.
+    pub pattern: Option<String>,
+
+    /// Which part to take. Negative values count from the end: -1 is the last part.
+    pub index: isize,
+}
+
+
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 pub enum Expression {
     Input { input: String },
     MultipleInput { input: Vec<String> },
 
-    Replace { replace: ReplaceMapping },
+    Replace {
+        replace: ReplaceMapping,
+
+        /// Apply the mapping entries one after another instead of in a single pass.
+        #[serde(default)]
+        sequential: bool,
+    },
     ReplaceRegex { replace_regex: ReplaceRegex },
+    Extract { extract: Extract },
+    Classify { classify: Classify },
 
     Variable { var: String },
     Value { value: String },
 
+    Normalize { normalize: NormalizationForm },
+    TitleCase { title_case: TitleCase },
+
     Date { date: String },
     MultipleDate { date: Vec<String> },
 
+    Lookup { lookup: Lookup },
+
+    Pad { pad: Pad },
+    Truncate { truncate: Truncate },
+    Trim { trim: usize },
+    Split { split: Split },
+
+    FillDown { fill_down: FillDown },
+    Lag { lag: Lag },
+    RunningSum { running_sum: Window },
+    RowNumberBy { row_number_by: ColumnNames },
+    ChangeFlag { change_flag: Window },
+
     Operation(String),
 
     // Not supported yet
     From { from: String },
-    Trim { trim: usize },
+}
+
+
+/// Regular expression whose capture groups become output columns.
+#[derive(Debug, Deserialize)]
+pub struct CapturesOutputs {
+    pub pattern: String,
+
+    /// Output column name → capture group. All named groups, in order, if omitted.
+    pub outputs: Option<LinkedHashMap<String, CaptureGroup>>,
+}
+
+
+/// Separator whose parts become output columns.
+#[derive(Debug, Deserialize)]
+pub struct SplitOutputs {
+    pub separator: Option<String>,
+    pub pattern: Option<String>,
+
+    /// Output column name → index of the part; negative indices count from the end.
+    pub outputs: LinkedHashMap<String, isize>,
+}
+
+
+/// Split the value of a column and give each part an output row of its own.
+#[derive(Debug, Deserialize)]
+pub struct ExplodeOptions {
+    pub separator: Option<String>,
+    pub pattern: Option<String>,
+
+    #[serde(default)]
+    pub on_empty: EmptyParts,
+
+    /// Name of an extra output column with the number of the part, starting from 1.
+    pub index_column: Option<String>,
+}
+
+
+/// Column definition with options beyond a plain chain of steps.
+///
+/// With `captures` or `split` the column produces several output columns, and its own
+/// name is not used as a header; the output names are.
+///
+/// With `match` the column is a template: it gives an output column for every input column
+/// whose header matches the regular expression. The steps start from the value of that input
+/// column, and the name of the column is a template filled with the capture groups, like
+/// `${1}_clean`.
+#[derive(Debug, Deserialize)]
+pub struct DetailedColumn {
+    #[serde(rename = "match")]
+    pub header_pattern: Option<String>,
+
+    /// Overrides the policy of the config for this column.
+    pub missing_columns: Option<MissingColumns>,
+
+    #[serde(default)]
+    pub steps: Vec<Expression>,
+
+    pub captures: Option<CapturesOutputs>,
+    pub split: Option<SplitOutputs>,
+    pub explode: Option<ExplodeOptions>,
 }
 
 
@@ -46,11 +288,319 @@ pub enum Expression {
 pub enum Column {
     Input(String),
     Expressions(Vec<Expression>),
+    Detailed(Box<DetailedColumn>),
+}
+
+
+/// Checks of one input or output column value. If several checks are given, all of them
+/// must pass.
+#[derive(Debug, Deserialize)]
+pub struct ValueCondition {
+    /// Output column to check.
+    pub column: Option<String>,
+
+    /// Input column to check.
+    pub input: Option<String>,
+
+    pub equals: Option<String>,
+    pub matches: Option<String>,
+    pub empty: Option<bool>,
+
+    pub gt: Option<f64>,
+    pub gte: Option<f64>,
+    pub lt: Option<f64>,
+    pub lte: Option<f64>,
+
+    /// Dates to compare with, in YYYY-MM-DD format.
+    pub before: Option<String>,
+    pub after: Option<String>,
+
+    /// Format of the dates in the column; YYYY-MM-DD by default.
+    pub date_format: Option<String>,
+}
+
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Condition {
+    All { all: Vec<Condition> },
+    Any { any: Vec<Condition> },
+    Not { not: Box<Condition> },
+    Value(Box<ValueCondition>),
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct Dedupe {
+    /// Output column(s) which identify a row.
+    pub key: ColumnNames,
+
+    #[serde(default)]
+    pub keep: Keep,
+
+    /// Above this number of distinct keys, rows are deduplicated on disk. Unlimited if omitted.
+    pub max_keys_in_memory: Option<usize>,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct Sort {
+    pub by: Vec<SortColumn>,
+
+    /// Rows sorted in memory at a time; beyond that, sorted runs are spilled to disk.
+    pub max_rows_in_memory: Option<usize>,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct GroupBy {
+    /// Output column(s) identifying a group.
+    pub keys: ColumnNames,
+
+    /// Name of the aggregated output column → how to compute it.
+    pub aggregates: LinkedHashMap<String, Aggregate>,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct Join {
+    /// Names of the joined sources.
+    pub left: String,
+    pub right: String,
+
+    pub left_key: ColumnNames,
+    pub right_key: ColumnNames,
+
+    #[serde(default, rename = "type")]
+    pub join_type: JoinType,
+
+    #[serde(default)]
+    pub build: BuildSide,
+}
+
+
+/// Named input files, read instead of the standard input or the command line files.
+#[derive(Debug, Deserialize)]
+pub struct Sources {
+    /// Source name → file, relative to the configuration file.
+    pub files: LinkedHashMap<String, String>,
+
+    pub join: Join,
+}
+
+
+/// Turn a set of input columns into rows of header and value (wide to long).
+#[derive(Debug, Deserialize)]
+pub struct UnpivotOptions {
+    /// Input columns to unpivot, by name...
+    pub columns: Option<Vec<String>>,
+
+    /// ...or by a regular expression their headers match.
+    pub pattern: Option<String>,
+
+    /// Name of the new input column holding the header of an unpivoted column.
+    pub key: String,
+
+    /// Name of the new input column holding its value.
+    pub value: String,
+
+    #[serde(default)]
+    pub skip_empty: bool,
+}
+
+
+/// Turn the values of one output column into columns of their own (long to wide).
+#[derive(Debug, Deserialize)]
+pub struct Pivot {
+    /// Output column(s) identifying a row of the pivoted output.
+    pub keys: ColumnNames,
+
+    /// Output column whose values become the new headers.
+    pub column: String,
+
+    /// Output column whose values fill the new columns.
+    pub value: String,
+
+    /// The new columns, in this order. Other values of `column` are dropped. If omitted,
+    /// every value gets a column, in order of first appearance.
+    pub columns: Option<Vec<String>>,
+}
+
+
+/// Where the passed through input columns go, relative to the explicit ones.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PassthroughPosition {
+    First,
+    #[default]
+    Last,
+}
+
+
+/// Copy the input columns not used by the explicit columns or the filter to the output.
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PassthroughOptions {
+    /// Only input columns whose headers match this regular expression...
+    pub include: Option<String>,
+
+    /// ...and do not match this one.
+    pub exclude: Option<String>,
+
+    /// Input column → output column name.
+    #[serde(default)]
+    pub rename: LinkedHashMap<String, String>,
+
+    #[serde(default)]
+    pub position: PassthroughPosition,
+}
+
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Passthrough {
+    Enabled(bool),
+    Detailed(PassthroughOptions),
+}
+
+
+/// What to output and where.
+#[derive(Debug, Deserialize, Default)]
+pub struct OutputSpec {
+    /// File to write to, relative to the configuration file. Standard output if omitted.
+    pub(crate) file: Option<String>,
+
+    /// Output column(s) splitting the rows into several files; `file` is then a template
+    /// naming them, like `out/{region}/{year}.csv`.
+    pub(crate) partition_by: Option<ColumnNames>,
+
+    /// With `partition_by`, the number of files open at a time.
+    pub(crate) max_open_files: Option<usize>,
+
+    #[serde(default)]
+    pub(crate) columns: LinkedHashMap<String, Column>,
+
+    /// Copy the other input columns as well.
+    pub(crate) passthrough: Option<Passthrough>,
+
+    /// Rows which do not satisfy this condition are dropped. Also spelled `where`.
+    #[serde(alias = "where")]
+    pub(crate) filter: Option<Condition>,
+
+    /// Rows which satisfy this condition are dropped.
+    pub(crate) skip_if: Option<Condition>,
+
+    /// Drop rows repeating the key of a previous row.
+    pub(crate) dedupe: Option<Dedupe>,
+
+    /// Output one row per group instead of one row per input row.
+    pub(crate) group_by: Option<GroupBy>,
+
+    /// Order of the output rows.
+    pub(crate) sort: Option<Sort>,
+
+    /// Reshape the final output, turning the values of a column into columns.
+    pub(crate) pivot: Option<Pivot>,
+}
+
+
+impl OutputSpec {
+    /// Fold `skip_if` into `filter`, so that only the latter has to be compiled.
+    pub(crate) fn merge_skip_if(&mut self) {
+        if let Some(skip_if) = self.skip_if.take() {
+            let skip = Condition::Not { not: Box::new(skip_if) };
+
+            self.filter = Some(match self.filter.take() {
+                Some(filter) => Condition::All { all: vec![filter, skip] },
+                None => skip,
+            });
+        }
+    }
+}
+
+
+/// Where the data is in the input files. The `line-number` step counts the data rows
+/// only, so the first row after the headers is number 1 whatever comes before it.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct InputOptions {
+    /// Lines dropped at the very beginning, before reading anything as CSV.
+    #[serde(default)]
+    pub skip_lines: usize,
+
+    /// Row holding the headers, counting from 1 after the skipped lines. Rows before it are dropped.
+    #[serde(default = "default_header_row")]
+    pub header_row: usize,
+
+    /// Rows dropped at the end. Cannot be combined with `footer_pattern`.
+    #[serde(default)]
+    pub skip_footer_lines: usize,
+
+    /// The first row matching this regular expression, and every row after it, are the
+    /// footer. The row is matched as parsed, with quotes removed and its values joined
+    /// by commas: `"Total, net",60` is matched as `Total, net,60`.
+    pub footer_pattern: Option<String>,
+}
+
+
+fn default_header_row() -> usize {
+    1
+}
+
+
+impl Default for InputOptions {
+    fn default() -> Self {
+        InputOptions {
+            skip_lines: 0,
+            header_row: default_header_row(),
+            skip_footer_lines: 0,
+            footer_pattern: None,
+        }
+    }
 }
 
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
+    #[allow(dead_code)]
     version: i8,
-    pub(crate) columns: LinkedHashMap<String, Column>,
+
+    /// Join two input files; input columns are then named like `orders.id`.
+    pub(crate) sources: Option<Sources>,
+
+    /// Preamble and footer of the standard input or the input files.
+    #[serde(default)]
+    pub(crate) input: InputOptions,
+
+    #[serde(default)]
+    pub(crate) header_matching: HeaderMatching,
+
+    #[serde(default)]
+    pub(crate) missing_columns: MissingColumns,
+
+    /// Unpivot input rows before computing the columns.
+    pub(crate) unpivot: Option<UnpivotOptions>,
+
+    /// The single output, described at the top level of the config if `outputs` is not given.
+    #[serde(skip)]
+    pub(crate) output: OutputSpec,
+
+    /// Several named outputs computed from one read of the input.
+    pub(crate) outputs: Option<LinkedHashMap<String, OutputSpec>>,
+
+    /// Directory of the configuration file; relative paths in the config are resolved against it.
+    #[serde(skip)]
+    pub(crate) base_directory: PathBuf,
+}
+
+
+impl Config {
+    /// Outputs to produce, by name; the single output of a config without `outputs` has none.
+    pub fn output_specs(&self) -> Vec<(Option<&str>, &OutputSpec)> {
+        match &self.outputs {
+            Some(outputs) => outputs.iter().map(
+                |(name, spec)| (Some(name.as_str()), spec),
+            ).collect(),
+            None => vec![(None, &self.output)],
+        }
+    }
 }
@@ -0,0 +1,60 @@
+use csv::StringRecord;
+
+use crate::compile::models::PassthroughOptions;
+use crate::compile::replace::compile_regex;
+use crate::transform::{ColumnChain, ColumnOutput, Transformation};
+
+
+/// Input columns copied to the output as they are: those selected by the patterns,
+/// except the ones the explicit columns or the filter already read, or whose (renamed)
+/// name is already taken by an explicit column.
+pub fn compile_passthrough(
+    passthrough: &PassthroughOptions,
+    input_headers: &StringRecord,
+    consumed: &[usize],
+    explicit_headers: &[String],
+) -> Result<(Vec<String>, Vec<ColumnChain>), String> {
+    let include = passthrough.include.as_deref().map(compile_regex).transpose()?;
+    let exclude = passthrough.exclude.as_deref().map(compile_regex).transpose()?;
+
+    for input_header in passthrough.rename.keys() {
+        if !input_headers.iter().any(|header| header == input_header) {
+            eprintln!(
+                "Warning: passthrough renames input column '{}' which does not exist.",
+                input_header,
+            );
+        }
+    }
+
+    let mut headers = vec![];
+    let mut columns = vec![];
+
+    for (index, input_header) in input_headers.iter().enumerate() {
+        if consumed.contains(&index) {
+            continue;
+        }
+
+        let selected = include.as_ref().is_none_or(|pattern| pattern.is_match(input_header))
+            && !exclude.as_ref().is_some_and(|pattern| pattern.is_match(input_header));
+
+        if !selected {
+            continue;
+        }
+
+        let header = passthrough.rename.get(input_header).cloned().unwrap_or(
+            input_header.to_string(),
+        );
+
+        if explicit_headers.contains(&header) || headers.contains(&header) {
+            continue;
+        }
+
+        headers.push(header);
+        columns.push(ColumnChain {
+            transformations: vec![Transformation::Input(index)],
+            output: ColumnOutput::Single,
+        });
+    }
+
+    Ok((headers, columns))
+}
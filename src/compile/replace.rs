@@ -1,17 +1,24 @@
+use aho_corasick::{AhoCorasickBuilder, MatchKind};
 use regex::Regex;
 
-use crate::compile::models::{MaybeSomeTransformation, ReplaceRegex};
+use crate::compile::models::{MaybeSomeTransformation, ReplaceMapping, ReplaceRegex};
 use crate::transform::Transformation;
 
-/// Accepts a number of mappings from regular expressions
-pub fn compile_replace_regex(replace_regex: &ReplaceRegex) -> MaybeSomeTransformation {
-    let pattern = Regex::new(replace_regex.pattern.as_str()).map_err(
+/// Compile a regular expression, explaining what went wrong if it is invalid.
+pub fn compile_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(
         |err| format!(
             "Cannot parse regular expression:\n\n  {}\n\nbecause: {}",
-            replace_regex.pattern,
-            err.to_string(),
+            pattern,
+            err,
         )
-    )?;
+    )
+}
+
+
+/// Accepts a number of mappings from regular expressions
+pub fn compile_replace_regex(replace_regex: &ReplaceRegex) -> MaybeSomeTransformation {
+    let pattern = compile_regex(replace_regex.pattern.as_str())?;
 
     let replace = replace_regex.replace.clone();
 
@@ -20,3 +27,27 @@ pub fn compile_replace_regex(replace_regex: &ReplaceRegex) -> MaybeSomeTransform
         replace,
     }))
 }
+
+
+/// Compile a literal replacement mapping into an Aho-Corasick automaton, which replaces all
+/// the keys in one pass. Where keys overlap, the leftmost longest one wins, so the result
+/// does not depend on the order of the mapping.
+///
+/// `sequential: true` keeps the old behavior: every entry is applied in turn to the result
+/// of the previous one.
+pub fn compile_replace(replace: &ReplaceMapping, sequential: bool) -> MaybeSomeTransformation {
+    if sequential {
+        return Ok(Some(Transformation::ReplaceSequential {
+            replace: replace.clone(),
+        }));
+    }
+
+    let automaton = Box::new(AhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(replace.keys()));
+
+    Ok(Some(Transformation::Replace {
+        automaton,
+        replacements: replace.values().cloned().collect(),
+    }))
+}
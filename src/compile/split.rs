@@ -0,0 +1,27 @@
+use crate::compile::models::{MaybeSomeTransformation, Split};
+use crate::compile::replace::compile_regex;
+use crate::transform::{Separator, Transformation};
+
+
+/// Split either by a literal separator or by a regular expression, but not both.
+pub fn compile_separator(
+    separator: &Option<String>,
+    pattern: &Option<String>,
+) -> Result<Separator, String> {
+    match (separator, pattern) {
+        (Some(separator), None) => Ok(Separator::Literal(separator.clone())),
+        (None, Some(pattern)) => Ok(Separator::Pattern(compile_regex(pattern)?)),
+
+        _ => Err(
+            "Exactly one of 'separator' and 'pattern' must be specified for split.".to_string(),
+        ),
+    }
+}
+
+
+pub fn compile_split(split: &Split) -> MaybeSomeTransformation {
+    Ok(Some(Transformation::Split {
+        separator: compile_separator(&split.separator, &split.pattern)?,
+        index: split.index,
+    }))
+}
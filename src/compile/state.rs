@@ -0,0 +1,127 @@
+use crate::compile::headers::InputColumnIndexByName;
+use crate::compile::models::{
+    ColumnNames,
+    FillDown,
+    Lag,
+    MaybeSomeTransformation,
+    Window,
+};
+use crate::transform::Transformation;
+
+
+/// Numbers the stateful steps of the config in order. Every input file must give the
+/// same output columns, which column templates would otherwise change, so the numbering
+/// is the same for every file and the state carries over from one file to the next.
+#[derive(Default)]
+pub struct StateSlots {
+    count: usize,
+}
+
+
+impl StateSlots {
+    pub fn allocate(&mut self) -> usize {
+        self.count += 1;
+        self.count - 1
+    }
+}
+
+
+/// Resolve the input columns a step keys its state by. None means a single state for all rows.
+fn compile_key_columns(
+    names: Option<&ColumnNames>,
+    step_name: &str,
+    input_column_index_by_name: &InputColumnIndexByName,
+) -> Result<Vec<usize>, String> {
+    match names {
+        None => Ok(vec![]),
+        Some(names) => names.to_vec().iter().map(
+            |name| input_column_index_by_name.get(name)?.ok_or(format!(
+                "Input column '{}' for {} not found.",
+                name, step_name,
+            )),
+        ).collect(),
+    }
+}
+
+
+pub fn compile_fill_down(
+    fill_down: &FillDown,
+    input_column_index_by_name: &InputColumnIndexByName,
+    slots: &mut StateSlots,
+) -> MaybeSomeTransformation {
+    Ok(Some(Transformation::FillDown {
+        slot: slots.allocate(),
+        reset_on: compile_key_columns(
+            fill_down.reset_on.as_ref(),
+            "fill_down",
+            input_column_index_by_name,
+        )?,
+    }))
+}
+
+
+pub fn compile_lag(
+    lag: &Lag,
+    input_column_index_by_name: &InputColumnIndexByName,
+    slots: &mut StateSlots,
+) -> MaybeSomeTransformation {
+    let column = input_column_index_by_name.get(&lag.column)?.ok_or(format!(
+        "Input column '{}' for lag not found.",
+        lag.column,
+    ))?;
+
+    if lag.offset == 0 {
+        return Err("Lag offset must be at least 1.".to_string());
+    }
+
+    Ok(Some(Transformation::Lag {
+        slot: slots.allocate(),
+        column,
+        offset: lag.offset,
+        by: compile_key_columns(lag.by.as_ref(), "lag", input_column_index_by_name)?,
+    }))
+}
+
+
+pub fn compile_running_sum(
+    running_sum: &Window,
+    input_column_index_by_name: &InputColumnIndexByName,
+    slots: &mut StateSlots,
+) -> MaybeSomeTransformation {
+    Ok(Some(Transformation::RunningSum {
+        slot: slots.allocate(),
+        by: compile_key_columns(
+            running_sum.by.as_ref(),
+            "running_sum",
+            input_column_index_by_name,
+        )?,
+    }))
+}
+
+
+pub fn compile_row_number_by(
+    keys: &ColumnNames,
+    input_column_index_by_name: &InputColumnIndexByName,
+    slots: &mut StateSlots,
+) -> MaybeSomeTransformation {
+    Ok(Some(Transformation::RowNumberBy {
+        slot: slots.allocate(),
+        by: compile_key_columns(Some(keys), "row_number_by", input_column_index_by_name)?,
+    }))
+}
+
+
+pub fn compile_change_flag(
+    change_flag: &Window,
+    input_column_index_by_name: &InputColumnIndexByName,
+    slots: &mut StateSlots,
+) -> MaybeSomeTransformation {
+    Ok(Some(Transformation::ChangeFlag {
+        slot: slots.allocate(),
+        by: compile_key_columns(
+            change_flag.by.as_ref(),
+            "change_flag",
+            input_column_index_by_name,
+        )?,
+    }))
+}
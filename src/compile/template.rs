@@ -0,0 +1,86 @@
+use csv::StringRecord;
+use linked_hash_map::LinkedHashMap;
+
+use crate::compile::models::Column;
+use crate::compile::replace::compile_regex;
+
+
+/// A column definition to compile under the given output name. Columns generated from a
+/// template read the input column they were generated for first.
+pub struct ExpandedColumn<'a> {
+    pub name: String,
+    pub column: &'a Column,
+    pub input: Option<usize>,
+}
+
+
+/// Replace every column template with the columns it generates for the actual input headers.
+pub fn expand_column_templates<'a>(
+    columns: &'a LinkedHashMap<String, Column>,
+    input_headers: &StringRecord,
+) -> Result<Vec<ExpandedColumn<'a>>, String> {
+    let mut expanded = vec![];
+
+    for (name, column) in columns.iter() {
+        let detailed_column = match column {
+            Column::Detailed(detailed_column) if detailed_column.header_pattern.is_some() => detailed_column,
+
+            _ => {
+                expanded.push(ExpandedColumn { name: name.clone(), column, input: None });
+                continue;
+            },
+        };
+
+        if detailed_column.captures.is_some()
+            || detailed_column.split.is_some()
+            || detailed_column.explode.is_some() {
+            return Err(format!(
+                "Column template '{}' cannot have 'captures', 'split' or 'explode'.",
+                name,
+            ));
+        }
+
+        let pattern = compile_regex(detailed_column.header_pattern.as_deref().unwrap())?;
+        let count = expanded.len();
+
+        for (index, header) in input_headers.iter().enumerate() {
+            if let Some(captures) = pattern.captures(header) {
+                let mut generated_name = String::new();
+                captures.expand(name, &mut generated_name);
+
+                expanded.push(ExpandedColumn { name: generated_name, column, input: Some(index) });
+            }
+        }
+
+        if expanded.len() == count {
+            eprintln!("Warning: no input column matches the template of column '{}'.", name);
+        }
+    }
+
+    Ok(expanded)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_column_templates() {
+        let columns: LinkedHashMap<String, Column> = serde_yaml::from_str(r#"
+            id: id
+            "${1}_clean":
+              match: "^amount_(.*)$"
+              steps: [strip]
+        "#).unwrap();
+
+        let headers = StringRecord::from(vec!["amount_net", "id", "amount_tax"]);
+        let expanded = expand_column_templates(&columns, &headers).unwrap();
+
+        let names: Vec<(&str, Option<usize>)> = expanded.iter().map(
+            |column| (column.name.as_str(), column.input),
+        ).collect();
+
+        assert_eq!(names, vec![("id", None), ("net_clean", Some(0)), ("tax_clean", Some(2))]);
+    }
+}
@@ -0,0 +1,69 @@
+use csv::StringRecord;
+
+use crate::compile::models::UnpivotOptions;
+use crate::compile::replace::compile_regex;
+use crate::transform::Unpivot;
+
+
+/// Returns the input headers as the rest of the configuration sees them: the kept input
+/// columns, then the key and value columns.
+pub fn compile_unpivot(
+    unpivot: &UnpivotOptions,
+    headers: &StringRecord,
+) -> Result<(StringRecord, Unpivot), String> {
+    let unpivoted_indices: Vec<usize> = match (&unpivot.columns, &unpivot.pattern) {
+        (Some(columns), None) => columns.iter().map(
+            |column| headers.iter().position(|header| header == column).ok_or(format!(
+                "Column '{}' to unpivot is not among the input columns.",
+                column,
+            ))
+        ).collect::<Result<_, String>>()?,
+
+        (None, Some(pattern)) => {
+            let pattern = compile_regex(pattern)?;
+
+            headers.iter().enumerate().filter(
+                |(_, header)| pattern.is_match(header),
+            ).map(
+                |(index, _)| index,
+            ).collect()
+        },
+
+        _ => return Err(
+            "Unpivot needs either 'columns' or 'pattern', but not both.".to_string(),
+        ),
+    };
+
+    if unpivoted_indices.is_empty() {
+        eprintln!("Warning: no input columns to unpivot, so no rows will be output.");
+    }
+
+    let kept: Vec<usize> = (0..headers.len()).filter(
+        |index| !unpivoted_indices.contains(index),
+    ).collect();
+
+    let mut unpivoted_headers: StringRecord = kept.iter().map(
+        |index| &headers[*index],
+    ).collect();
+
+    for name in [&unpivot.key, &unpivot.value] {
+        if unpivoted_headers.iter().any(|header| header == name) {
+            return Err(format!(
+                "Unpivot column '{}' clashes with an input column of the same name.",
+                name,
+            ));
+        }
+
+        unpivoted_headers.push_field(name);
+    }
+
+    let unpivoted = unpivoted_indices.into_iter().map(
+        |index| (headers[index].as_bytes().to_vec(), index),
+    ).collect();
+
+    Ok((unpivoted_headers, Unpivot {
+        kept,
+        unpivoted,
+        skip_empty: unpivot.skip_empty,
+    }))
+}
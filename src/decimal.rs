@@ -0,0 +1,161 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+
+// Digits kept after the input scale when dividing, as for an average.
+const DIVISION_EXTRA_DIGITS: u32 = 4;
+
+
+/// A decimal number kept as a whole number of units of 10^-scale, so that adding up
+/// amounts like `0.1` and `0.2` gives exactly `0.3`. The scale is the largest number of
+/// decimal places among the values it was computed from, and it is printed with as many.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Decimal {
+    units: i128,
+    scale: u32,
+}
+
+
+impl Decimal {
+    /// Parse a number like `-12.50` or `1.5e3`. Surrounding spaces are ignored.
+    pub fn parse(text: &str) -> Option<Decimal> {
+        let text = text.trim();
+
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(position) => (&text[..position], text[position + 1..].parse::<i32>().ok()?),
+            None => (text, 0),
+        };
+
+        let (negative, digits) = match mantissa.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+        };
+
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if whole.is_empty() && fraction.is_empty() {
+            return None;
+        }
+
+        let mut units: i128 = 0;
+        for digit in whole.chars().chain(fraction.chars()) {
+            units = units.checked_mul(10)?.checked_add(digit.to_digit(10)? as i128)?;
+        }
+
+        if negative {
+            units = -units;
+        }
+
+        let scale = fraction.len() as i64 - exponent as i64;
+
+        if scale >= 0 {
+            Some(Decimal { units, scale: u32::try_from(scale).ok()? })
+        } else {
+            let factor = 10_i128.checked_pow(u32::try_from(-scale).ok()?)?;
+            Some(Decimal { units: units.checked_mul(factor)?, scale: 0 })
+        }
+    }
+
+    /// The same number with more decimal places.
+    fn with_scale(self, scale: u32) -> Option<Decimal> {
+        let factor = 10_i128.checked_pow(scale - self.scale)?;
+
+        Some(Decimal { units: self.units.checked_mul(factor)?, scale })
+    }
+
+    /// None if the sum is too large to keep exactly.
+    pub fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        let scale = self.scale.max(other.scale);
+        let (left, right) = (self.with_scale(scale)?, other.with_scale(scale)?);
+
+        Some(Decimal { units: left.units.checked_add(right.units)?, scale })
+    }
+
+    /// Divide by a count, rounding half away from zero to a few more places than the
+    /// scale. Trailing zeros beyond the scale are dropped, so `135.75 / 2` is `67.875`.
+    pub fn checked_div(self, divisor: usize) -> Option<Decimal> {
+        let divisor = i128::try_from(divisor).ok().filter(|divisor| *divisor > 0)?;
+        let dividend = self.with_scale(self.scale + DIVISION_EXTRA_DIGITS)?;
+
+        let mut units = dividend.units / divisor;
+        let remainder = dividend.units % divisor;
+
+        if remainder.unsigned_abs() * 2 >= divisor.unsigned_abs() {
+            units += dividend.units.signum();
+        }
+
+        let mut quotient = Decimal { units, scale: dividend.scale };
+
+        while quotient.scale > self.scale && quotient.units % 10 == 0 {
+            quotient.units /= 10;
+            quotient.scale -= 1;
+        }
+
+        Some(quotient)
+    }
+}
+
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", self.units.unsigned_abs(), width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+
+        if self.units < 0 {
+            write!(f, "-")?;
+        }
+
+        if fraction.is_empty() {
+            write!(f, "{}", whole)
+        } else {
+            write!(f, "{}.{}", whole, fraction)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(text: &str) -> Decimal {
+        Decimal::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(decimal(" 12.50 ").to_string(), "12.50");
+        assert_eq!(decimal("-0.05").to_string(), "-0.05");
+        assert_eq!(decimal("+7").to_string(), "7");
+        assert_eq!(decimal(".5").to_string(), "0.5");
+        assert_eq!(decimal("1.5e3").to_string(), "1500");
+        assert_eq!(decimal("25e-3").to_string(), "0.025");
+
+        for text in ["", "-", ".", "abc", "1.2.3", "1e", "1,000"] {
+            assert_eq!(Decimal::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_add() {
+        let sum = decimal("0.1").checked_add(decimal("0.2")).unwrap();
+        assert_eq!(sum.to_string(), "0.3");
+
+        let sum = decimal("100.50").checked_add(decimal("-135")).unwrap();
+        assert_eq!(sum.to_string(), "-34.50");
+    }
+
+    #[test]
+    fn test_div() {
+        let average = |text: &str, count: usize| decimal(text).checked_div(count).unwrap().to_string();
+
+        assert_eq!(average("0.3", 2), "0.15");
+        assert_eq!(average("20", 2), "10");
+        assert_eq!(average("3", 2), "1.5");
+        assert_eq!(average("4", 3), "1.3333");
+        assert_eq!(average("-5", 3), "-1.6667");
+        assert_eq!(average("10.00", 4), "2.50");
+        assert_eq!(Decimal::default().checked_div(0), None);
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use csv::{ByteRecord, StringRecord};
+use serde::Deserialize;
+
+use crate::spill::{merge, SpillDirectory, SpillReader, SpillWriter};
+
+
+/// Number of files the records are spread over once the keys do not fit in memory.
+/// Each of them is later deduplicated in memory on its own.
+const PARTITION_COUNT: usize = 64;
+
+
+/// Which one of the duplicate rows gets to the output.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Keep {
+    #[default]
+    First,
+    Last,
+}
+
+
+type Key = Vec<Vec<u8>>;
+
+
+enum State {
+    /// Keep first: rows with new keys are passed through immediately.
+    FirstInMemory(HashSet<Key>),
+
+    /// Keep last: the latest row of every key, with its sequence number.
+    LastInMemory(HashMap<Key, (usize, ByteRecord)>),
+
+    /// Too many keys: records are spread over partition files by key. Keys in `emitted`
+    /// have already been passed through before the spill started.
+    Spilled {
+        directory: SpillDirectory,
+        partitions: Vec<SpillWriter>,
+        emitted: HashSet<Key>,
+    },
+}
+
+
+/// Drops output rows whose key columns repeat a row seen before, across all input files.
+pub struct Deduplicator {
+    key_columns: Vec<String>,
+    key_indices: Vec<usize>,
+    keep: Keep,
+    max_keys_in_memory: Option<usize>,
+    state: State,
+    sequence: usize,
+    emitted_count: usize,
+}
+
+
+fn partition_of(key: &Key) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+
+    (hasher.finish() % PARTITION_COUNT as u64) as usize
+}
+
+
+impl Deduplicator {
+    pub fn new(key_columns: Vec<String>, keep: Keep, max_keys_in_memory: Option<usize>) -> Self {
+        let state = match keep {
+            Keep::First => State::FirstInMemory(HashSet::new()),
+            Keep::Last => State::LastInMemory(HashMap::new()),
+        };
+
+        Deduplicator {
+            key_columns,
+            key_indices: vec![],
+            keep,
+            max_keys_in_memory,
+            state,
+            sequence: 0,
+            emitted_count: 0,
+        }
+    }
+
+    /// Find the key columns among the output headers of the current input file.
+    pub fn resolve(&mut self, headers: &StringRecord) -> Result<(), String> {
+        self.key_indices = self.key_columns.iter().map(
+            |column| headers.iter().position(|header| header == column).ok_or(format!(
+                "Dedupe key column '{}' is not among the output columns.",
+                column,
+            )),
+        ).collect::<Result<_, String>>()?;
+
+        Ok(())
+    }
+
+    fn key_of(&self, record: &ByteRecord) -> Key {
+        self.key_indices.iter().map(
+            |index| record.get(*index).unwrap_or(b"").to_vec(),
+        ).collect()
+    }
+
+    fn start_spilling(&mut self) -> Result<(), String> {
+        let mut directory = SpillDirectory::new()?;
+        let mut partitions = (0..PARTITION_COUNT).map(
+            |_| directory.create(),
+        ).collect::<Result<Vec<SpillWriter>, String>>()?;
+
+        let previous_state = std::mem::replace(&mut self.state, State::FirstInMemory(HashSet::new()));
+
+        let emitted = match previous_state {
+            State::FirstInMemory(seen) => seen,
+
+            State::LastInMemory(latest) => {
+                for (key, (sequence, record)) in latest.iter() {
+                    partitions[partition_of(key)].write(*sequence, record)?;
+                }
+
+                HashSet::new()
+            },
+
+            State::Spilled { .. } => unreachable!("Deduplicator is spilling already"),
+        };
+
+        self.state = State::Spilled { directory, partitions, emitted };
+
+        Ok(())
+    }
+
+    /// Take the next output record. Returns it back if it can be output right away.
+    pub fn push(&mut self, record: ByteRecord) -> Result<Option<ByteRecord>, String> {
+        let key = self.key_of(&record);
+        let sequence = self.sequence;
+        self.sequence += 1;
+
+        let limit = self.max_keys_in_memory.unwrap_or(usize::MAX);
+
+        match &mut self.state {
+            State::FirstInMemory(seen) => {
+                if seen.contains(&key) {
+                    return Ok(None);
+                }
+
+                if seen.len() < limit {
+                    seen.insert(key);
+                    self.emitted_count += 1;
+                    return Ok(Some(record));
+                }
+
+                self.start_spilling()?;
+            },
+
+            State::LastInMemory(latest) => {
+                latest.insert(key, (sequence, record));
+
+                if latest.len() > limit {
+                    self.start_spilling()?;
+                }
+
+                return Ok(None);
+            },
+
+            State::Spilled { .. } => {},
+        }
+
+        if let State::Spilled { partitions, emitted, .. } = &mut self.state {
+            if !emitted.contains(&key) {
+                partitions[partition_of(&key)].write(sequence, &record)?;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Deduplicate one partition in memory and write the survivors ordered by sequence.
+    fn deduplicate_partition(
+        &self,
+        reader: SpillReader,
+        directory: &mut SpillDirectory,
+    ) -> Result<(SpillReader, usize), String> {
+        let mut survivors: HashMap<Key, (usize, ByteRecord)> = HashMap::new();
+
+        for item in reader {
+            let (sequence, record) = item?;
+            let key = self.key_of(&record);
+
+            // Records of one partition are not necessarily in sequence order
+            let replace = match (survivors.get(&key), self.keep) {
+                (None, _) => true,
+                (Some((survivor, _)), Keep::First) => sequence < *survivor,
+                (Some((survivor, _)), Keep::Last) => sequence > *survivor,
+            };
+
+            if replace {
+                survivors.insert(key, (sequence, record));
+            }
+        }
+
+        let mut survivors: Vec<(usize, ByteRecord)> = survivors.into_values().collect();
+        survivors.sort_by_key(|(sequence, _)| *sequence);
+
+        let mut writer = directory.create()?;
+        for (sequence, record) in survivors.iter() {
+            writer.write(*sequence, record)?;
+        }
+
+        Ok((writer.into_reader()?, survivors.len()))
+    }
+
+    /// Output whatever has been held back, in the original order.
+    pub fn finish(
+        mut self,
+        emit: &mut dyn FnMut(ByteRecord) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let state = std::mem::replace(&mut self.state, State::FirstInMemory(HashSet::new()));
+
+        match state {
+            State::FirstInMemory(_) => {},
+
+            State::LastInMemory(latest) => {
+                let mut records: Vec<(usize, ByteRecord)> = latest.into_values().collect();
+                records.sort_by_key(|(sequence, _)| *sequence);
+
+                self.emitted_count += records.len();
+                for (_, record) in records {
+                    emit(record)?;
+                }
+            },
+
+            State::Spilled { mut directory, partitions, .. } => {
+                let mut readers = vec![];
+
+                for partition in partitions {
+                    let (reader, count) = self.deduplicate_partition(
+                        partition.into_reader()?,
+                        &mut directory,
+                    )?;
+
+                    self.emitted_count += count;
+                    readers.push(reader);
+                }
+
+                merge(readers, |_| (), emit)?;
+            },
+        }
+
+        eprintln!(
+            "Dedupe dropped {} duplicate row(s).",
+            self.sequence - self.emitted_count,
+        );
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(keep: Keep, max_keys_in_memory: Option<usize>) -> Vec<String> {
+        let mut deduplicator = Deduplicator::new(vec!["key".to_string()], keep, max_keys_in_memory);
+        deduplicator.resolve(&StringRecord::from(vec!["key", "value"])).unwrap();
+
+        let rows = vec![("a", "1"), ("b", "2"), ("a", "3"), ("c", "4"), ("b", "5"), ("d", "6")];
+        let mut output = vec![];
+
+        for (key, value) in rows {
+            if let Some(record) = deduplicator.push(ByteRecord::from(vec![key, value])).unwrap() {
+                output.push(record);
+            }
+        }
+
+        deduplicator.finish(&mut |record| {
+            output.push(record);
+            Ok(())
+        }).unwrap();
+
+        output.iter().map(
+            |record| String::from_utf8(record[1].to_vec()).unwrap(),
+        ).collect()
+    }
+
+    #[test]
+    fn test_keep_first() {
+        assert_eq!(run(Keep::First, None), vec!["1", "2", "4", "6"]);
+    }
+
+    #[test]
+    fn test_keep_last() {
+        assert_eq!(run(Keep::Last, None), vec!["3", "4", "5", "6"]);
+    }
+
+    #[test]
+    fn test_spilled_results_match_in_memory_ones() {
+        assert_eq!(run(Keep::First, Some(1)), vec!["1", "2", "4", "6"]);
+        assert_eq!(run(Keep::Last, Some(1)), vec!["3", "4", "5", "6"]);
+    }
+}
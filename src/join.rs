@@ -0,0 +1,303 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::path::Path;
+
+use csv::{ByteRecord, ByteRecordsIntoIter, Reader, ReaderBuilder, StringRecord};
+use serde::Deserialize;
+
+use crate::compile::Sources;
+
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum JoinType {
+    /// Only rows with a match on the other side.
+    #[default]
+    Inner,
+
+    /// Every row of the left source; the right columns are empty if there is no match.
+    Left,
+}
+
+
+/// Which source is loaded into memory. The other one is streamed, and the output follows its order.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildSide {
+    Left,
+    #[default]
+    Right,
+}
+
+
+type Key = Vec<Vec<u8>>;
+
+
+fn open(name: &str, path: &Path) -> Result<(StringRecord, Reader<File>), String> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path).map_err(
+            |err| format!("Cannot open source '{}' ({}): {}", name, path.display(), err),
+        )?;
+
+    let headers = reader.headers().map_err(
+        |err| format!("Cannot read headers of source '{}': {}", name, err),
+    )?.clone();
+
+    Ok((headers, reader))
+}
+
+
+fn key_indices(name: &str, headers: &StringRecord, key: &[String]) -> Result<Vec<usize>, String> {
+    key.iter().map(
+        |column| headers.iter().position(|header| header == column).ok_or(format!(
+            "Key column '{}' not found in source '{}'.",
+            column, name,
+        )),
+    ).collect()
+}
+
+
+/// Rows with an empty key part never match anything, as NULLs do not in SQL.
+fn key_of(record: &ByteRecord, indices: &[usize]) -> Option<Key> {
+    let key: Key = indices.iter().map(
+        |index| record.get(*index).unwrap_or(b"").to_vec(),
+    ).collect();
+
+    if key.iter().any(|part| part.is_empty()) {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+
+/// Rows of the source loaded into memory, by key.
+struct BuildTable {
+    rows: Vec<ByteRecord>,
+    matched: Vec<bool>,
+    index: HashMap<Key, Vec<usize>>,
+}
+
+
+impl BuildTable {
+    fn load(reader: Reader<File>, name: &str, key: &[usize]) -> Result<BuildTable, String> {
+        let rows: Vec<ByteRecord> = reader.into_byte_records().collect::<Result<_, _>>().map_err(
+            |err| format!("Cannot read source '{}': {}", name, err),
+        )?;
+
+        let mut index: HashMap<Key, Vec<usize>> = HashMap::new();
+        for (position, row) in rows.iter().enumerate() {
+            if let Some(key) = key_of(row, key) {
+                index.entry(key).or_default().push(position);
+            }
+        }
+
+        Ok(BuildTable { matched: vec![false; rows.len()], rows, index })
+    }
+}
+
+
+/// Pad or cut a record to the number of its source's columns, so that the columns of
+/// the other source stay in place.
+fn extend_with(joined: &mut ByteRecord, record: Option<&ByteRecord>, width: usize) {
+    for index in 0..width {
+        joined.push_field(record.and_then(|record| record.get(index)).unwrap_or(b""));
+    }
+}
+
+
+/// Combined rows of the two sources: the left columns, then the right ones.
+pub struct JoinedRecords {
+    probe: ByteRecordsIntoIter<File>,
+    probe_key: Vec<usize>,
+    build: BuildTable,
+    build_side: BuildSide,
+    join_type: JoinType,
+    left_width: usize,
+    right_width: usize,
+    pending: VecDeque<ByteRecord>,
+    finished: bool,
+}
+
+
+impl JoinedRecords {
+    fn combine(&self, left: Option<&ByteRecord>, right: Option<&ByteRecord>) -> ByteRecord {
+        let mut joined = ByteRecord::new();
+        extend_with(&mut joined, left, self.left_width);
+        extend_with(&mut joined, right, self.right_width);
+        joined
+    }
+
+    fn probe(&mut self, record: &ByteRecord) {
+        let matches = key_of(record, &self.probe_key).and_then(
+            |key| self.build.index.get(&key),
+        ).cloned().unwrap_or_default();
+
+        for position in matches.iter() {
+            self.build.matched[*position] = true;
+
+            let build_record = &self.build.rows[*position];
+            let joined = match self.build_side {
+                BuildSide::Right => self.combine(Some(record), Some(build_record)),
+                BuildSide::Left => self.combine(Some(build_record), Some(record)),
+            };
+
+            self.pending.push_back(joined);
+        }
+
+        if matches.is_empty() {
+            if let (JoinType::Left, BuildSide::Right) = (self.join_type, self.build_side) {
+                let joined = self.combine(Some(record), None);
+                self.pending.push_back(joined);
+            }
+        }
+    }
+
+    /// With the left side in memory, a left join learns which of its rows had no match
+    /// only at the end.
+    fn finish(&mut self) {
+        if let (JoinType::Left, BuildSide::Left) = (self.join_type, self.build_side) {
+            let unmatched: Vec<ByteRecord> = self.build.rows.iter().zip(
+                self.build.matched.iter(),
+            ).filter(
+                |(_, matched)| !**matched,
+            ).map(
+                |(row, _)| self.combine(Some(row), None),
+            ).collect();
+
+            self.pending.extend(unmatched);
+        }
+
+        self.finished = true;
+    }
+}
+
+
+impl Iterator for JoinedRecords {
+    type Item = Result<ByteRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Some(Ok(record));
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            match self.probe.next() {
+                Some(Ok(record)) => self.probe(&record),
+                Some(Err(err)) => return Some(Err(err.to_string())),
+                None => self.finish(),
+            }
+        }
+    }
+}
+
+
+/// Open both sources, load one of them, and return the headers of the joined rows —
+/// every column prefixed with its source name, like `orders.id` — with the rows.
+pub fn join(sources: &Sources, base_directory: &Path) -> Result<(StringRecord, JoinedRecords), String> {
+    let join = &sources.join;
+
+    let path_of = |name: &String| sources.files.get(name).map(
+        |file| base_directory.join(file),
+    ).ok_or(format!("Source '{}' to join is not among the source files.", name));
+
+    let (left_headers, left_reader) = open(&join.left, &path_of(&join.left)?)?;
+    let (right_headers, right_reader) = open(&join.right, &path_of(&join.right)?)?;
+
+    let left_key = key_indices(&join.left, &left_headers, &join.left_key.to_vec())?;
+    let right_key = key_indices(&join.right, &right_headers, &join.right_key.to_vec())?;
+
+    if left_key.len() != right_key.len() {
+        return Err(format!(
+            "Join has {} left key column(s) but {} right key column(s).",
+            left_key.len(), right_key.len(),
+        ));
+    }
+
+    let (build, probe, probe_key) = match join.build {
+        BuildSide::Right => (
+            BuildTable::load(right_reader, &join.right, &right_key)?,
+            left_reader.into_byte_records(),
+            left_key,
+        ),
+        BuildSide::Left => (
+            BuildTable::load(left_reader, &join.left, &left_key)?,
+            right_reader.into_byte_records(),
+            right_key,
+        ),
+    };
+
+    let headers: StringRecord = left_headers.iter().map(
+        |header| format!("{}.{}", join.left, header),
+    ).chain(right_headers.iter().map(
+        |header| format!("{}.{}", join.right, header),
+    )).collect();
+
+    Ok((headers, JoinedRecords {
+        probe,
+        probe_key,
+        build,
+        build_side: join.build,
+        join_type: join.join_type,
+        left_width: left_headers.len(),
+        right_width: right_headers.len(),
+        pending: VecDeque::new(),
+        finished: false,
+    }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(join_type: &str, build: &str) -> Vec<String> {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::write(directory.path().join("orders.csv"), "id,customer\n1,alice\n2,bob\n3,carol\n").unwrap();
+        std::fs::write(directory.path().join("items.csv"), "order_id,sku\n2,tea\n1,cup\n2,pot\n,lid\n").unwrap();
+
+        let sources: Sources = serde_yaml::from_str(&format!("
+            files:
+              orders: orders.csv
+              items: items.csv
+            join:
+              left: orders
+              right: items
+              left_key: id
+              right_key: order_id
+              type: {}
+              build: {}
+        ", join_type, build)).unwrap();
+
+        let (headers, records) = join(&sources, directory.path()).unwrap();
+        assert_eq!(headers, vec!["orders.id", "orders.customer", "items.order_id", "items.sku"]);
+
+        records.map(|record| {
+            let record = record.unwrap();
+            String::from_utf8(record.iter().collect::<Vec<_>>().join(&b","[..])).unwrap()
+        }).collect()
+    }
+
+    #[test]
+    fn test_inner_join() {
+        assert_eq!(run("inner", "right"), vec!["1,alice,1,cup", "2,bob,2,tea", "2,bob,2,pot"]);
+    }
+
+    #[test]
+    fn test_left_join_keeps_unmatched_rows_whichever_side_is_built() {
+        assert_eq!(
+            run("left", "right"),
+            vec!["1,alice,1,cup", "2,bob,2,tea", "2,bob,2,pot", "3,carol,,"],
+        );
+
+        assert_eq!(
+            run("left", "left"),
+            vec!["2,bob,2,tea", "1,alice,1,cup", "2,bob,2,pot", "3,carol,,"],
+        );
+    }
+}
@@ -7,11 +7,21 @@ mod worker;
 mod options;
 mod compile;
 mod transform;
+mod reader;
 mod writer;
+mod dedupe;
+mod spill;
+mod sort;
+mod aggregate;
+mod decimal;
+mod join;
+mod pivot;
+mod output;
+mod partition;
 
 /// Configure the logger which will print log to stderr.
 /// Well, it is currently no-op
-fn configure_logging(_log_format: LogFormat) -> () {
+fn configure_logging(_log_format: LogFormat) {
     simple_logger::init().unwrap();
 }
 
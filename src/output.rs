@@ -0,0 +1,178 @@
+use crossbeam_channel::Sender;
+use csv::{ByteRecord, StringRecord};
+
+use crate::aggregate::Aggregator;
+use crate::compile::OutputSpec;
+use crate::dedupe::Deduplicator;
+use crate::pivot::Pivoter;
+use crate::sort::Sorter;
+
+
+/// Everything between the filtered output records and the writer thread:
+/// dedupe → group by → sort → pivot → writer. Lives across all the input files.
+pub struct Output {
+    tx: Sender<ByteRecord>,
+    deduplicator: Option<Deduplicator>,
+    aggregator: Option<Aggregator>,
+    sorter: Option<Sorter>,
+    pivoter: Option<Pivoter>,
+}
+
+
+fn write(record: ByteRecord, tx: &Sender<ByteRecord>) -> Result<(), String> {
+    tx.send(record).map_err(
+        |_| "The output writer has stopped.".to_string(),
+    )
+}
+
+
+/// Pass a record on to the pivoter if there is one, or straight to the writer.
+fn pivot(
+    record: ByteRecord,
+    pivoter: &mut Option<Pivoter>,
+    tx: &Sender<ByteRecord>,
+) -> Result<(), String> {
+    match pivoter {
+        Some(pivoter) => {
+            pivoter.push(record);
+            Ok(())
+        },
+        None => write(record, tx),
+    }
+}
+
+
+/// Pass a record on to the sorter if there is one, or further down.
+fn sort(
+    record: ByteRecord,
+    sorter: &mut Option<Sorter>,
+    pivoter: &mut Option<Pivoter>,
+    tx: &Sender<ByteRecord>,
+) -> Result<(), String> {
+    match sorter {
+        Some(sorter) => sorter.push(record),
+        None => pivot(record, pivoter, tx),
+    }
+}
+
+
+/// Pass a record on to the aggregator if there is one, or further down.
+fn aggregate(
+    record: ByteRecord,
+    aggregator: &mut Option<Aggregator>,
+    sorter: &mut Option<Sorter>,
+    pivoter: &mut Option<Pivoter>,
+    tx: &Sender<ByteRecord>,
+) -> Result<(), String> {
+    match aggregator {
+        Some(aggregator) => {
+            aggregator.push(record);
+            Ok(())
+        },
+        None => sort(record, sorter, pivoter, tx),
+    }
+}
+
+
+impl Output {
+    pub fn new(tx: Sender<ByteRecord>, spec: &OutputSpec) -> Output {
+        let deduplicator = spec.dedupe.as_ref().map(
+            |dedupe| Deduplicator::new(
+                dedupe.key.to_vec(),
+                dedupe.keep,
+                dedupe.max_keys_in_memory,
+            ),
+        );
+
+        let aggregator = spec.group_by.as_ref().map(
+            |group_by| Aggregator::new(
+                group_by.keys.to_vec(),
+                group_by.aggregates.clone(),
+            ),
+        );
+
+        let sorter = spec.sort.as_ref().map(
+            |sort| Sorter::new(sort.by.clone(), sort.max_rows_in_memory),
+        );
+
+        let pivoter = spec.pivot.as_ref().map(
+            |pivot| Pivoter::new(
+                pivot.keys.to_vec(),
+                pivot.column.clone(),
+                pivot.value.clone(),
+                pivot.columns.clone(),
+            ),
+        );
+
+        Output { tx, deduplicator, aggregator, sorter, pivoter }
+    }
+
+    /// Get ready for the records of a new input file with the given output headers.
+    pub fn prepare(&mut self, headers: &StringRecord, write_headers: bool) -> Result<(), String> {
+        let mut headers = headers.clone();
+
+        if let Some(deduplicator) = &mut self.deduplicator {
+            deduplicator.resolve(&headers)?;
+        }
+
+        if let Some(aggregator) = &mut self.aggregator {
+            headers = aggregator.resolve(&headers)?;
+        }
+
+        if let Some(sorter) = &mut self.sorter {
+            sorter.resolve(&headers)?;
+        }
+
+        // Pivoted headers depend on the data, so the pivoter writes them at the end
+        if let Some(pivoter) = &mut self.pivoter {
+            pivoter.resolve(&headers)?;
+        } else if write_headers {
+            write(headers.into_byte_record(), &self.tx)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn send(&mut self, record: ByteRecord) -> Result<(), String> {
+        let maybe_record = match &mut self.deduplicator {
+            Some(deduplicator) => deduplicator.push(record)?,
+            None => Some(record),
+        };
+
+        match maybe_record {
+            Some(record) => aggregate(
+                record,
+                &mut self.aggregator,
+                &mut self.sorter,
+                &mut self.pivoter,
+                &self.tx,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Send whatever has been held back, stage by stage. The channel is closed afterwards.
+    pub fn finish(self) -> Result<(), String> {
+        let Output { tx, deduplicator, mut aggregator, mut sorter, mut pivoter } = self;
+
+        if let Some(deduplicator) = deduplicator {
+            deduplicator.finish(
+                &mut |record| aggregate(record, &mut aggregator, &mut sorter, &mut pivoter, &tx),
+            )?;
+        }
+
+        if let Some(aggregator) = aggregator {
+            aggregator.finish(&mut |record| sort(record, &mut sorter, &mut pivoter, &tx))?;
+        }
+
+        if let Some(sorter) = sorter {
+            sorter.finish(&mut |record| pivot(record, &mut pivoter, &tx))?;
+        }
+
+        if let Some(pivoter) = pivoter {
+            pivoter.finish(&mut |record| write(record, &tx))?;
+        }
+
+        Ok(())
+    }
+}
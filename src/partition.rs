@@ -0,0 +1,240 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use csv::{ByteRecord, Writer};
+use linked_hash_map::LinkedHashMap;
+
+
+// Files kept open at a time if the config does not say.
+const DEFAULT_MAX_OPEN_FILES: usize = 64;
+
+
+#[derive(Debug, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Column(String),
+}
+
+
+/// Split a file name template like `out/{region}/{year}.csv` into text and column names.
+fn parse_template(template: &str) -> Result<Vec<TemplatePart>, String> {
+    let mut parts = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or(format!(
+            "Unclosed '{{' in file name template '{}'.",
+            template,
+        ))? + start;
+
+        if start > 0 {
+            parts.push(TemplatePart::Literal(rest[..start].to_string()));
+        }
+
+        parts.push(TemplatePart::Column(rest[start + 1..end].to_string()));
+        rest = &rest[end + 1..];
+    }
+
+    if !rest.is_empty() {
+        parts.push(TemplatePart::Literal(rest.to_string()));
+    }
+
+    Ok(parts)
+}
+
+
+/// A value as a piece of a file path: it must not lead into another directory.
+fn path_safe(value: &[u8]) -> String {
+    let value = String::from_utf8_lossy(value).replace(['/', '\\'], "_");
+
+    match value.as_str() {
+        "" | "." | ".." => "_".to_string(),
+        _ => value,
+    }
+}
+
+
+/// Writes every row to the file named after the values of its partition columns. Every
+/// file starts with the headers. Only so many files are open at a time; when one more
+/// is needed, the least recently used one is closed, and is appended to if needed again.
+pub struct PartitionedWriter {
+    template: Vec<TemplatePart>,
+    base_directory: PathBuf,
+    max_open_files: usize,
+
+    headers: Option<ByteRecord>,
+    column_indices: Vec<Option<usize>>,
+
+    open: LinkedHashMap<PathBuf, Writer<File>>,
+    created: HashSet<PathBuf>,
+}
+
+
+impl PartitionedWriter {
+    pub fn new(
+        template: &str,
+        columns: &[String],
+        base_directory: &Path,
+        max_open_files: Option<usize>,
+    ) -> Result<PartitionedWriter, String> {
+        let template = parse_template(template)?;
+
+        let template_columns: Vec<&String> = template.iter().filter_map(
+            |part| match part {
+                TemplatePart::Column(column) => Some(column),
+                TemplatePart::Literal(_) => None,
+            },
+        ).collect();
+
+        for column in template_columns.iter() {
+            if !columns.contains(column) {
+                return Err(format!(
+                    "File name template refers to '{}', which is not a partition column.",
+                    column,
+                ));
+            }
+        }
+
+        for column in columns.iter() {
+            if !template_columns.contains(&column) {
+                return Err(format!(
+                    "Partition column '{}' is missing from the file name template.",
+                    column,
+                ));
+            }
+        }
+
+        Ok(PartitionedWriter {
+            template,
+            base_directory: base_directory.to_path_buf(),
+            max_open_files: max_open_files.unwrap_or(DEFAULT_MAX_OPEN_FILES).max(1),
+            headers: None,
+            column_indices: vec![],
+            open: LinkedHashMap::new(),
+            created: HashSet::new(),
+        })
+    }
+
+    /// The first record is the headers; they tell where the partition columns are.
+    pub fn write(&mut self, record: ByteRecord) -> Result<(), String> {
+        if self.headers.is_none() {
+            self.column_indices = self.template.iter().map(
+                |part| match part {
+                    TemplatePart::Literal(_) => Ok(None),
+                    TemplatePart::Column(column) => record.iter().position(
+                        |header| header == column.as_bytes(),
+                    ).map(Some).ok_or(format!(
+                        "Partition column '{}' is not among the output columns.",
+                        column,
+                    )),
+                },
+            ).collect::<Result<_, String>>()?;
+
+            self.headers = Some(record);
+            return Ok(());
+        }
+
+        let path = self.path_of(&record);
+        self.writer(path)?.write_byte_record(&record).map_err(|err| err.to_string())
+    }
+
+    fn path_of(&self, record: &ByteRecord) -> PathBuf {
+        let name: String = self.template.iter().zip(self.column_indices.iter()).map(
+            |(part, index)| match (part, index) {
+                (TemplatePart::Literal(text), _) => text.clone(),
+                (TemplatePart::Column(_), Some(index)) => path_safe(
+                    record.get(*index).unwrap_or(b""),
+                ),
+                (TemplatePart::Column(_), None) => unreachable!(),
+            },
+        ).collect();
+
+        self.base_directory.join(name)
+    }
+
+    fn writer(&mut self, path: PathBuf) -> Result<&mut Writer<File>, String> {
+        if self.open.contains_key(&path) {
+            return Ok(self.open.get_refresh(&path).unwrap());
+        }
+
+        if self.open.len() >= self.max_open_files {
+            if let Some((_, mut writer)) = self.open.pop_front() {
+                writer.flush().map_err(|err| err.to_string())?;
+            }
+        }
+
+        let is_new = self.created.insert(path.clone());
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(
+                |err| format!("Cannot create directory {}: {}", directory.display(), err),
+            )?;
+        }
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(is_new)
+            .append(!is_new)
+            .open(&path).map_err(
+                |err| format!("Cannot open output file {}: {}", path.display(), err),
+            )?;
+
+        let mut writer = Writer::from_writer(file);
+        if is_new {
+            if let Some(headers) = &self.headers {
+                writer.write_byte_record(headers).map_err(|err| err.to_string())?;
+            }
+        }
+
+        self.open.insert(path.clone(), writer);
+        Ok(self.open.get_mut(&path).unwrap())
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        for (_, writer) in self.open.iter_mut() {
+            writer.flush().map_err(|err| err.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template() {
+        assert_eq!(parse_template("out/{region}/{year}.csv").unwrap(), vec![
+            TemplatePart::Literal("out/".to_string()),
+            TemplatePart::Column("region".to_string()),
+            TemplatePart::Literal("/".to_string()),
+            TemplatePart::Column("year".to_string()),
+            TemplatePart::Literal(".csv".to_string()),
+        ]);
+
+        assert!(parse_template("out/{region.csv").is_err());
+    }
+
+    #[test]
+    fn test_files_are_reopened_for_appending() {
+        let directory = tempfile::tempdir().unwrap();
+        let columns = vec!["region".to_string()];
+        let mut writer = PartitionedWriter::new(
+            "{region}.csv", &columns, directory.path(), Some(1),
+        ).unwrap();
+
+        for row in [vec!["region", "amount"], vec!["north", "1"], vec!["south", "2"], vec!["north", "3"]] {
+            writer.write(ByteRecord::from(row)).unwrap();
+        }
+
+        writer.finish().unwrap();
+
+        let read = |name: &str| fs::read_to_string(directory.path().join(name)).unwrap();
+        assert_eq!(read("north.csv"), "region,amount\nnorth,1\nnorth,3\n");
+        assert_eq!(read("south.csv"), "region,amount\nsouth,2\n");
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use csv::{ByteRecord, StringRecord};
+use linked_hash_map::LinkedHashMap;
+
+
+type Key = Vec<Vec<u8>>;
+
+
+/// Turns the values of one output column into columns: one output row per key, with
+/// the value of every (key, column) pair in its cell. Rows are kept in memory and
+/// output in the order their keys first appear in.
+pub struct Pivoter {
+    keys: Vec<String>,
+    column: String,
+    value: String,
+
+    /// Headers of the new columns, either given or in order of appearance.
+    columns: Vec<Vec<u8>>,
+    fixed_columns: bool,
+
+    key_indices: Vec<usize>,
+    column_index: usize,
+    value_index: usize,
+
+    rows: LinkedHashMap<Key, HashMap<Vec<u8>, Vec<u8>>>,
+    overwritten_count: usize,
+    dropped_count: usize,
+}
+
+
+impl Pivoter {
+    pub fn new(keys: Vec<String>, column: String, value: String, columns: Option<Vec<String>>) -> Pivoter {
+        Pivoter {
+            keys,
+            column,
+            value,
+            fixed_columns: columns.is_some(),
+            columns: columns.unwrap_or_default().into_iter().map(String::into_bytes).collect(),
+            key_indices: vec![],
+            column_index: 0,
+            value_index: 0,
+            rows: LinkedHashMap::new(),
+            overwritten_count: 0,
+            dropped_count: 0,
+        }
+    }
+
+    /// Find the key, column and value columns among the output headers of the current input file.
+    pub fn resolve(&mut self, headers: &StringRecord) -> Result<(), String> {
+        let position = |column: &String| headers.iter().position(
+            |header| header == column,
+        ).ok_or(format!(
+            "Column '{}' to pivot by is not among the output columns.",
+            column,
+        ));
+
+        self.key_indices = self.keys.iter().map(position).collect::<Result<_, String>>()?;
+        self.column_index = position(&self.column)?;
+        self.value_index = position(&self.value)?;
+
+        Ok(())
+    }
+
+    pub fn push(&mut self, record: ByteRecord) {
+        let field = |index: usize| record.get(index).unwrap_or(b"").to_vec();
+
+        let column = field(self.column_index);
+
+        if !self.columns.contains(&column) {
+            if self.fixed_columns {
+                self.dropped_count += 1;
+                return;
+            }
+
+            self.columns.push(column.clone());
+        }
+
+        let key: Key = self.key_indices.iter().map(|index| field(*index)).collect();
+
+        let row = self.rows.entry(key).or_insert_with(HashMap::new);
+        if row.insert(column, field(self.value_index)).is_some() {
+            self.overwritten_count += 1;
+        }
+    }
+
+    /// Emits the headers first, since they are only known now.
+    pub fn finish(
+        self,
+        emit: &mut dyn FnMut(ByteRecord) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let mut headers: ByteRecord = self.keys.iter().map(String::as_bytes).collect();
+        for column in self.columns.iter() {
+            headers.push_field(column);
+        }
+
+        emit(headers)?;
+
+        for (key, row) in self.rows {
+            let mut record: ByteRecord = key.iter().collect();
+
+            for column in self.columns.iter() {
+                record.push_field(row.get(column).map(Vec::as_slice).unwrap_or(b""));
+            }
+
+            emit(record)?;
+        }
+
+        if self.overwritten_count > 0 {
+            eprintln!(
+                "Warning: pivot met {} value(s) for a key and column which already had one; the last one was kept.",
+                self.overwritten_count,
+            );
+        }
+
+        if self.dropped_count > 0 {
+            eprintln!(
+                "Pivot dropped {} row(s) whose '{}' is not among the listed columns.",
+                self.dropped_count, self.column,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(columns: Option<Vec<String>>) -> Vec<String> {
+        let mut pivoter = Pivoter::new(
+            vec!["region".to_string()],
+            "month".to_string(),
+            "total".to_string(),
+            columns,
+        );
+
+        pivoter.resolve(&StringRecord::from(vec!["month", "region", "total"])).unwrap();
+
+        for (month, region, total) in [
+            ("02", "north", "5"),
+            ("01", "north", "10"),
+            ("01", "south", "7"),
+            ("03", "south", "1"),
+        ] {
+            pivoter.push(ByteRecord::from(vec![month, region, total]));
+        }
+
+        let mut lines = vec![];
+        pivoter.finish(&mut |record| {
+            let fields: Vec<String> = record.iter().map(
+                |field| String::from_utf8_lossy(field).into_owned(),
+            ).collect();
+
+            lines.push(fields.join(","));
+            Ok(())
+        }).unwrap();
+
+        lines
+    }
+
+    #[test]
+    fn test_columns_in_order_of_appearance() {
+        assert_eq!(run(None), vec!["region,02,01,03", "north,5,10,", "south,,7,1"]);
+    }
+
+    #[test]
+    fn test_listed_columns() {
+        assert_eq!(
+            run(Some(vec!["01".to_string(), "02".to_string()])),
+            vec!["region,01,02", "north,10,5", "south,7,"],
+        );
+    }
+}
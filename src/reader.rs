@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader};
+
+use csv::{ByteRecord, ByteRecordsIntoIter, ReaderBuilder, StringRecord};
+use regex::Regex;
+
+use crate::compile::{compile_regex, InputOptions};
+
+
+/// Data rows of an input, without the footer. The last `footer_lines` rows read are held
+/// back until it is known that more rows follow them.
+pub struct Records<R: io::Read> {
+    records: ByteRecordsIntoIter<BufReader<R>>,
+    footer_lines: usize,
+    footer_pattern: Option<Regex>,
+    lookahead: VecDeque<ByteRecord>,
+    finished: bool,
+}
+
+
+fn is_footer(record: &ByteRecord, pattern: &Regex) -> bool {
+    let line: Vec<String> = record.iter().map(
+        |field| String::from_utf8_lossy(field).into_owned(),
+    ).collect();
+
+    pattern.is_match(&line.join(","))
+}
+
+
+impl<R: io::Read> Iterator for Records<R> {
+    type Item = Result<ByteRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            match self.records.next() {
+                // Whatever is still held back is the footer
+                None => self.finished = true,
+
+                Some(Err(err)) => return Some(Err(err.to_string())),
+
+                Some(Ok(record)) => {
+                    if self.footer_pattern.as_ref().is_some_and(|pattern| is_footer(&record, pattern)) {
+                        self.finished = true;
+                        continue;
+                    }
+
+                    self.lookahead.push_back(record);
+
+                    if self.lookahead.len() > self.footer_lines {
+                        return self.lookahead.pop_front().map(Ok);
+                    }
+                },
+            }
+        }
+
+        None
+    }
+}
+
+
+/// Skip the preamble lines as they are, without parsing them as CSV, since report
+/// metadata often is not valid CSV. Then find the headers and return them along with
+/// the data rows.
+pub fn read_csv<R: io::Read>(
+    source: R,
+    input: &InputOptions,
+) -> Result<(StringRecord, Records<R>), String> {
+    let mut source = BufReader::new(source);
+
+    let mut line = vec![];
+    for _ in 0..input.skip_lines {
+        line.clear();
+        source.read_until(b'\n', &mut line).map_err(|err| err.to_string())?;
+    }
+
+    if input.header_row == 0 {
+        return Err("Input header_row starts from 1.".to_string());
+    }
+
+    let mut records = ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(source)
+        .into_byte_records();
+
+    let mut headers = StringRecord::new();
+    for _ in 0..input.header_row {
+        headers = match records.next() {
+            Some(record) => StringRecord::from_byte_record(
+                record.map_err(|err| err.to_string())?,
+            ).map_err(|err| format!("Input headers are not valid UTF-8: {}", err))?,
+            None => StringRecord::new(),
+        };
+    }
+
+    let footer_pattern = input.footer_pattern.as_deref().map(compile_regex).transpose()?;
+
+    Ok((headers, Records {
+        records,
+        footer_lines: input.skip_footer_lines,
+        footer_pattern,
+        lookahead: VecDeque::new(),
+        finished: false,
+    }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(content: &str, yaml: &str) -> (Vec<String>, Vec<String>) {
+        let input: InputOptions = serde_yaml::from_str(yaml).unwrap();
+        let (headers, records) = read_csv(content.as_bytes(), &input).unwrap();
+
+        let rows = records.map(|record| {
+            let fields: Vec<String> = record.unwrap().iter().map(
+                |field| String::from_utf8_lossy(field).into_owned(),
+            ).collect();
+
+            fields.join(",")
+        }).collect();
+
+        (headers.iter().map(String::from).collect(), rows)
+    }
+
+    const REPORT: &str = "Bank \"statement\nExported: today\ndate,amount\n01,10\n02,20\n03,30\nTotal,60\nEnd of report\n";
+
+    #[test]
+    fn test_skip_preamble_and_footer_lines() {
+        let (headers, rows) = read(REPORT, "{skip_lines: 2, skip_footer_lines: 2}");
+
+        assert_eq!(headers, vec!["date", "amount"]);
+        assert_eq!(rows, vec!["01,10", "02,20", "03,30"]);
+    }
+
+    #[test]
+    fn test_header_row_and_footer_pattern() {
+        let (headers, rows) = read(
+            "a\nb\ndate,amount\n01,10\nTotal,10\n02,20\n",
+            "{header_row: 3, footer_pattern: '^Total,'}",
+        );
+
+        assert_eq!(headers, vec!["date", "amount"]);
+        assert_eq!(rows, vec!["01,10"]);
+    }
+
+    #[test]
+    fn test_footer_pattern_with_quoted_field() {
+        let (_, rows) = read(
+            "date,amount\n\"01, Jan\",10\n\"Total, net\",10\n",
+            "{footer_pattern: '^Total, net,'}",
+        );
+
+        assert_eq!(rows, vec!["01, Jan,10"]);
+    }
+}
@@ -0,0 +1,290 @@
+use std::cmp::{Ordering, Reverse};
+
+use chrono::NaiveDate;
+use csv::{ByteRecord, StringRecord};
+use serde::Deserialize;
+
+use crate::spill::{merge, SpillDirectory, SpillReader};
+
+
+/// Rows sorted in memory at a time, unless configured otherwise.
+const DEFAULT_MAX_ROWS_IN_MEMORY: usize = 1_000_000;
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+
+/// How the values of a sort column are compared.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortType {
+    #[default]
+    String,
+    Number,
+    Date,
+}
+
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SortColumn {
+    /// Output column to sort by.
+    pub column: String,
+
+    #[serde(default, rename = "type")]
+    pub sort_type: SortType,
+
+    /// Format of dates; YYYY-MM-DD by default.
+    pub format: Option<String>,
+
+    #[serde(default)]
+    pub direction: Direction,
+}
+
+
+/// f64 with total ordering, to be used in sort keys.
+#[derive(Debug, PartialEq)]
+pub struct Number(pub f64);
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+
+/// Typed value of a sort column.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortValue {
+    Number(Number),
+    Date(NaiveDate),
+    String(Vec<u8>),
+}
+
+
+/// Values which cannot be parsed as the column type are `Missing`, and go after all
+/// the others in either direction.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum KeyPart {
+    Asc(SortValue),
+    Desc(Reverse<SortValue>),
+    Missing,
+}
+
+
+type SortKey = Vec<KeyPart>;
+
+
+/// Parse a value as the given type; None if it is not a valid number or date.
+pub fn typed_value(sort_type: SortType, format: Option<&str>, bytes: &[u8]) -> Option<SortValue> {
+    let text = || std::str::from_utf8(bytes).ok().map(str::trim);
+
+    match sort_type {
+        SortType::String => Some(SortValue::String(bytes.to_vec())),
+
+        SortType::Number => text().and_then(
+            |text| text.parse().ok(),
+        ).map(
+            |number| SortValue::Number(Number(number)),
+        ),
+
+        SortType::Date => text().and_then(
+            |text| NaiveDate::parse_from_str(
+                text,
+                format.unwrap_or(DEFAULT_DATE_FORMAT),
+            ).ok(),
+        ).map(SortValue::Date),
+    }
+}
+
+
+/// Sorts the output rows; spills sorted runs to disk when they do not fit in memory, and
+/// merges them at the end. Rows with equal keys keep their original order.
+pub struct Sorter {
+    columns: Vec<SortColumn>,
+    indices: Vec<usize>,
+    max_rows_in_memory: usize,
+    buffer: Vec<(usize, ByteRecord)>,
+    directory: Option<SpillDirectory>,
+    runs: Vec<SpillReader>,
+    sequence: usize,
+}
+
+
+impl Sorter {
+    pub fn new(columns: Vec<SortColumn>, max_rows_in_memory: Option<usize>) -> Sorter {
+        Sorter {
+            columns,
+            indices: vec![],
+            max_rows_in_memory: max_rows_in_memory.unwrap_or(DEFAULT_MAX_ROWS_IN_MEMORY).max(1),
+            buffer: vec![],
+            directory: None,
+            runs: vec![],
+            sequence: 0,
+        }
+    }
+
+    /// Find the sort columns among the output headers of the current input file.
+    pub fn resolve(&mut self, headers: &StringRecord) -> Result<(), String> {
+        self.indices = self.columns.iter().map(
+            |sort_column| headers.iter().position(
+                |header| header == sort_column.column,
+            ).ok_or(format!(
+                "Sort column '{}' is not among the output columns.",
+                sort_column.column,
+            )),
+        ).collect::<Result<_, String>>()?;
+
+        Ok(())
+    }
+
+    fn key_of(&self, record: &ByteRecord) -> SortKey {
+        self.columns.iter().zip(self.indices.iter()).map(
+            |(sort_column, index)| {
+                let value = typed_value(
+                    sort_column.sort_type,
+                    sort_column.format.as_deref(),
+                    record.get(*index).unwrap_or(b""),
+                );
+
+                match (value, sort_column.direction) {
+                    (Some(value), Direction::Asc) => KeyPart::Asc(value),
+                    (Some(value), Direction::Desc) => KeyPart::Desc(Reverse(value)),
+                    (None, _) => KeyPart::Missing,
+                }
+            }
+        ).collect()
+    }
+
+    fn sort_buffer(&mut self) {
+        let mut buffer = std::mem::take(&mut self.buffer);
+
+        // Sequence numbers are unique, so this is stable
+        buffer.sort_by_cached_key(|(sequence, record)| (self.key_of(record), *sequence));
+
+        self.buffer = buffer;
+    }
+
+    fn spill(&mut self) -> Result<(), String> {
+        self.sort_buffer();
+
+        if self.directory.is_none() {
+            self.directory = Some(SpillDirectory::new()?);
+        }
+
+        let mut writer = self.directory.as_mut().unwrap().create()?;
+        for (sequence, record) in self.buffer.drain(..) {
+            writer.write(sequence, &record)?;
+        }
+
+        self.runs.push(writer.into_reader()?);
+
+        Ok(())
+    }
+
+    pub fn push(&mut self, record: ByteRecord) -> Result<(), String> {
+        self.buffer.push((self.sequence, record));
+        self.sequence += 1;
+
+        if self.buffer.len() >= self.max_rows_in_memory {
+            self.spill()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(
+        mut self,
+        emit: &mut dyn FnMut(ByteRecord) -> Result<(), String>,
+    ) -> Result<(), String> {
+        if self.runs.is_empty() {
+            self.sort_buffer();
+
+            for (_, record) in self.buffer.drain(..) {
+                emit(record)?;
+            }
+
+            return Ok(());
+        }
+
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+
+        let runs = std::mem::take(&mut self.runs);
+
+        merge(runs, |record| self.key_of(record), emit)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort(max_rows_in_memory: usize) -> Vec<String> {
+        let columns = vec![
+            SortColumn {
+                column: "amount".to_string(),
+                sort_type: SortType::Number,
+                format: None,
+                direction: Direction::Desc,
+            },
+            SortColumn {
+                column: "date".to_string(),
+                sort_type: SortType::Date,
+                format: Some("%d.%m.%Y".to_string()),
+                direction: Direction::Asc,
+            },
+        ];
+
+        let mut sorter = Sorter::new(columns, Some(max_rows_in_memory));
+        sorter.resolve(&StringRecord::from(vec!["id", "amount", "date"])).unwrap();
+
+        let rows = vec![
+            vec!["a", "9", "01.02.2020"],
+            vec!["b", "10", "05.01.2020"],
+            vec!["c", "9", "31.12.2019"],
+            vec!["d", "n/a", "01.01.2020"],
+            vec!["e", "10", "05.01.2020"],
+            vec!["f", "9", "not a date"],
+        ];
+
+        for row in rows {
+            sorter.push(ByteRecord::from(row)).unwrap();
+        }
+
+        let mut output = vec![];
+        sorter.finish(&mut |record| {
+            output.push(String::from_utf8(record[0].to_vec()).unwrap());
+            Ok(())
+        }).unwrap();
+
+        output
+    }
+
+    #[test]
+    fn test_sort_in_memory() {
+        assert_eq!(sort(100), vec!["b", "e", "c", "a", "f", "d"]);
+    }
+
+    #[test]
+    fn test_sort_with_spilled_runs() {
+        assert_eq!(sort(2), vec!["b", "e", "c", "a", "f", "d"]);
+    }
+}
@@ -0,0 +1,141 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::path::PathBuf;
+
+use csv::{ByteRecord, Reader, ReaderBuilder, Writer};
+use tempfile::TempDir;
+
+
+/// Temporary directory for records which do not fit in memory. Removed when dropped.
+pub struct SpillDirectory {
+    directory: TempDir,
+    file_count: usize,
+}
+
+
+/// Records are stored with their sequence number (their position in the output before any
+/// reordering) as the first field, so that the original order can always be restored.
+pub struct SpillWriter {
+    path: PathBuf,
+    writer: Writer<File>,
+}
+
+
+pub struct SpillReader {
+    reader: Reader<File>,
+}
+
+
+impl SpillDirectory {
+    pub fn new() -> Result<SpillDirectory, String> {
+        let directory = tempfile::Builder::new().prefix("ysv-").tempdir().map_err(
+            |err| format!("Cannot create a temporary directory: {}", err),
+        )?;
+
+        Ok(SpillDirectory { directory, file_count: 0 })
+    }
+
+    pub fn create(&mut self) -> Result<SpillWriter, String> {
+        self.file_count += 1;
+        let path = self.directory.path().join(format!("{}.csv", self.file_count));
+
+        let writer = Writer::from_path(&path).map_err(
+            |err| format!("Cannot create temporary file {}: {}", path.display(), err),
+        )?;
+
+        Ok(SpillWriter { path, writer })
+    }
+}
+
+
+impl SpillWriter {
+    pub fn write(&mut self, sequence: usize, record: &ByteRecord) -> Result<(), String> {
+        let mut spilled = ByteRecord::with_capacity(record.as_slice().len() + 20, record.len() + 1);
+        spilled.push_field(sequence.to_string().as_bytes());
+        spilled.extend(record.iter());
+
+        self.writer.write_byte_record(&spilled).map_err(
+            |err| format!("Cannot write temporary file {}: {}", self.path.display(), err),
+        )
+    }
+
+    /// Flush the file to disk and open it for reading.
+    pub fn into_reader(mut self) -> Result<SpillReader, String> {
+        self.writer.flush().map_err(
+            |err| format!("Cannot write temporary file {}: {}", self.path.display(), err),
+        )?;
+
+        let reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(&self.path).map_err(
+                |err| format!("Cannot read temporary file {}: {}", self.path.display(), err),
+            )?;
+
+        Ok(SpillReader { reader })
+    }
+}
+
+
+impl Iterator for SpillReader {
+    type Item = Result<(usize, ByteRecord), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut spilled = ByteRecord::new();
+
+        match self.reader.read_byte_record(&mut spilled) {
+            Ok(false) => None,
+            Err(err) => Some(Err(format!("Cannot read temporary file: {}", err))),
+
+            Ok(true) => {
+                let sequence = std::str::from_utf8(&spilled[0]).ok().and_then(
+                    |sequence| sequence.parse().ok(),
+                );
+
+                Some(sequence.map(
+                    |sequence| (sequence, spilled.iter().skip(1).collect()),
+                ).ok_or_else(
+                    || "Temporary file is corrupted.".to_string(),
+                ))
+            },
+        }
+    }
+}
+
+
+/// Merge several spill files, each of them already ordered by (key, sequence number),
+/// into one stream ordered the same way.
+pub fn merge<K: Ord, F: Fn(&ByteRecord) -> K>(
+    readers: Vec<SpillReader>,
+    key: F,
+    emit: &mut dyn FnMut(ByteRecord) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut readers = readers;
+    let mut heads: Vec<Option<ByteRecord>> = Vec::with_capacity(readers.len());
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+
+    for (index, reader) in readers.iter_mut().enumerate() {
+        heads.push(None);
+
+        if let Some(item) = reader.next() {
+            let (sequence, record) = item?;
+            heap.push(Reverse((key(&record), sequence, index)));
+            heads[index] = Some(record);
+        }
+    }
+
+    while let Some(Reverse((_, _, index))) = heap.pop() {
+        if let Some(record) = heads[index].take() {
+            emit(record)?;
+        }
+
+        if let Some(item) = readers[index].next() {
+            let (sequence, record) = item?;
+            heap.push(Reverse((key(&record), sequence, index)));
+            heads[index] = Some(record);
+        }
+    }
+
+    Ok(())
+}
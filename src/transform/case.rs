@@ -1,19 +1,149 @@
+use std::collections::HashMap;
+
 use crate::transform::{CellValue, ApplyResult};
 
 
+/// Words which title case must spell in a particular way ("McDonald", "O'Neil"),
+/// keyed by their lowercase form.
+pub type TitleCaseExceptions = HashMap<String, String>;
+
+
 /// Known string case transformations.
-pub enum StringCase {
+pub enum StringCase<'a> {
     Uppercase,
     Lowercase,
+    Title(&'a TitleCaseExceptions),
+    Sentence,
+    Snake,
+    Kebab,
+    Camel,
+}
+
+
+/// Uppercase the first character of a word, lowercase the rest.
+fn capitalize(word: &str) -> String {
+    let mut characters = word.chars();
+
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(
+            characters.flat_map(char::to_lowercase),
+        ).collect(),
+        None => String::new(),
+    }
+}
+
+
+fn title_case_word(word: &str, exceptions: &TitleCaseExceptions) -> String {
+    exceptions.get(&word.to_lowercase()).cloned().unwrap_or_else(
+        || capitalize(word),
+    )
+}
+
+
+/// Capitalize every word; words are separated by whitespace and hyphens, so that
+/// "smith-jones" becomes "Smith-Jones". Apostrophes do not start a new word.
+fn title_case(content: &str, exceptions: &TitleCaseExceptions) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut word = String::new();
+
+    for character in content.chars() {
+        if character.is_whitespace() || character == '-' {
+            result.push_str(&title_case_word(&word, exceptions));
+            result.push(character);
+            word.clear();
+        } else {
+            word.push(character);
+        }
+    }
+
+    result.push_str(&title_case_word(&word, exceptions));
+
+    result
 }
 
 
-/// Change a string to the upper or lower case.
+/// Lowercase everything except the first letter.
+fn sentence_case(content: &str) -> String {
+    let mut found_first_letter = false;
+
+    content.chars().flat_map(|character| {
+        if !found_first_letter && character.is_alphabetic() {
+            found_first_letter = true;
+            character.to_uppercase().collect::<Vec<char>>()
+        } else {
+            character.to_lowercase().collect()
+        }
+    }).collect()
+}
+
+
+/// Split a string into words for identifier-style cases. Words are separated by any
+/// non-alphanumeric characters and by case changes: "parseHTTPResponse" gives
+/// "parse", "HTTP", "Response".
+fn split_words(content: &str) -> Vec<String> {
+    let characters: Vec<char> = content.chars().collect();
+    let mut words = vec![];
+    let mut word = String::new();
+
+    for (index, character) in characters.iter().enumerate() {
+        if !character.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        if !word.is_empty() && character.is_uppercase() {
+            let previous = characters[index - 1];
+            let next_is_lowercase = characters.get(index + 1).is_some_and(
+                |next| next.is_lowercase(),
+            );
+
+            if !previous.is_uppercase() || next_is_lowercase {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+
+        word.push(*character);
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+
+fn join_lowercase_words(content: &str, separator: &str) -> String {
+    split_words(content).iter().map(
+        |word| word.to_lowercase(),
+    ).collect::<Vec<String>>().join(separator)
+}
+
+
+fn camel_case(content: &str) -> String {
+    split_words(content).iter().enumerate().map(
+        |(index, word)| if index == 0 {
+            word.to_lowercase()
+        } else {
+            capitalize(word)
+        }
+    ).collect()
+}
+
+
+/// Change a string to the requested case.
 pub fn apply_change_case(value: CellValue, case: StringCase) -> ApplyResult {
     if let CellValue::String(Some(content)) = value {
         Ok(CellValue::String(Some(match case {
             StringCase::Lowercase => content.to_lowercase(),
             StringCase::Uppercase => content.to_uppercase(),
+            StringCase::Title(exceptions) => title_case(&content, exceptions),
+            StringCase::Sentence => sentence_case(&content),
+            StringCase::Snake => join_lowercase_words(&content, "_"),
+            StringCase::Kebab => join_lowercase_words(&content, "-"),
+            StringCase::Camel => camel_case(&content),
         })))
 
     } else {
@@ -24,3 +154,41 @@ pub fn apply_change_case(value: CellValue, case: StringCase) -> ApplyResult {
         ))
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_case_exceptions() {
+        let mut exceptions = TitleCaseExceptions::new();
+        exceptions.insert("mcdonald".to_string(), "McDonald".to_string());
+        exceptions.insert("o'neil".to_string(), "O'Neil".to_string());
+
+        assert_eq!(
+            title_case("RONALD MCDONALD and shaquille o'neil-smith", &exceptions),
+            "Ronald McDonald And Shaquille O'Neil-Smith",
+        );
+    }
+
+    #[test]
+    fn test_sentence_case() {
+        assert_eq!(sentence_case("  hELLO World"), "  Hello world");
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words("parseHTTPResponse v2_final"),
+            vec!["parse", "HTTP", "Response", "v2", "final"],
+        );
+    }
+
+    #[test]
+    fn test_identifier_cases() {
+        assert_eq!(join_lowercase_words("First Name", "_"), "first_name");
+        assert_eq!(join_lowercase_words("firstName", "-"), "first-name");
+        assert_eq!(camel_case("first_name (legal)"), "firstNameLegal");
+    }
+}
@@ -0,0 +1,50 @@
+use regex::RegexSet;
+
+use crate::transform::{ApplyResult, CellValue};
+
+
+/// Index of the first rule (in config order) whose pattern matches the content.
+fn classify(content: &str, patterns: &RegexSet) -> Option<usize> {
+    patterns.matches(content).iter().next()
+}
+
+
+pub fn apply_classify(
+    value: CellValue,
+    patterns: &RegexSet,
+    values: &[String],
+    default: &Option<String>,
+) -> ApplyResult {
+    match value {
+        CellValue::String(maybe_content) => Ok(CellValue::String(
+            maybe_content.and_then(
+                |content| classify(&content, patterns),
+            ).map(
+                |index| values[index].clone(),
+            ).or_else(
+                || default.clone(),
+            )
+        )),
+
+        _ => Err(format!(
+            "Warning: cannot apply 'classify' transformation to a {} value '{:?}'.",
+            &value.type_name(),
+            &value,
+        )),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let patterns = RegexSet::new(["(?i)coffee", "(?i)starbucks"]).unwrap();
+
+        assert_eq!(classify("STARBUCKS COFFEE #123", &patterns), Some(0));
+        assert_eq!(classify("Starbucks", &patterns), Some(1));
+        assert_eq!(classify("Uber", &patterns), None);
+    }
+}
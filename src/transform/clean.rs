@@ -0,0 +1,138 @@
+use serde::Deserialize;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+use crate::transform::{ApplyResult, CellValue};
+
+
+/// Unicode normalization forms supported by the 'normalize' step.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum NormalizationForm {
+    Nfc,
+    Nfkc,
+}
+
+
+/// Known whitespace and Unicode cleanup transformations.
+pub enum Cleanup {
+    Strip,
+    CollapseWhitespace,
+    Normalize(NormalizationForm),
+    RemoveAccents,
+    RemoveControlChars,
+    ReplaceNbsp,
+}
+
+
+/// Characters which take no space on screen but still break comparisons and joins.
+fn is_zero_width(character: char) -> bool {
+    matches!(
+        character,
+        '\u{200B}'..='\u{200F}' | '\u{2060}'..='\u{2064}' | '\u{FEFF}'
+    )
+}
+
+
+fn is_non_breaking_space(character: char) -> bool {
+    matches!(character, '\u{00A0}' | '\u{2007}' | '\u{202F}')
+}
+
+
+fn strip(content: &str) -> String {
+    content.trim_matches(
+        |character: char| character.is_whitespace() || is_zero_width(character),
+    ).to_string()
+}
+
+
+fn collapse_whitespace(content: &str) -> String {
+    content.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+
+fn normalize(content: &str, form: NormalizationForm) -> String {
+    match form {
+        NormalizationForm::Nfc => content.nfc().collect(),
+        NormalizationForm::Nfkc => content.nfkc().collect(),
+    }
+}
+
+
+/// Decompose the string, drop the combining marks and compose whatever is left back.
+fn remove_accents(content: &str) -> String {
+    content.nfd().filter(
+        |character| !is_combining_mark(*character),
+    ).nfc().collect()
+}
+
+
+fn remove_control_chars(content: &str) -> String {
+    content.chars().filter(
+        |character| !character.is_control() && !is_zero_width(*character),
+    ).collect()
+}
+
+
+fn replace_nbsp(content: &str) -> String {
+    content.chars().map(
+        |character| if is_non_breaking_space(character) { ' ' } else { character },
+    ).collect()
+}
+
+
+fn clean(content: &str, cleanup: &Cleanup) -> String {
+    match cleanup {
+        Cleanup::Strip => strip(content),
+        Cleanup::CollapseWhitespace => collapse_whitespace(content),
+        Cleanup::Normalize(form) => normalize(content, *form),
+        Cleanup::RemoveAccents => remove_accents(content),
+        Cleanup::RemoveControlChars => remove_control_chars(content),
+        Cleanup::ReplaceNbsp => replace_nbsp(content),
+    }
+}
+
+
+pub fn apply_cleanup(value: CellValue, cleanup: Cleanup) -> ApplyResult {
+    if let CellValue::String(maybe_content) = value {
+        Ok(CellValue::String(maybe_content.map(
+            |content| clean(&content, &cleanup),
+        )))
+
+    } else {
+        Err(format!(
+            "Warning: cannot apply the cleanup transformation to a {} value '{:?}'.",
+            &value.type_name(),
+            &value,
+        ))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_zero_width() {
+        assert_eq!(strip("\u{FEFF} Acme\u{00A0}\u{200B}"), "Acme");
+    }
+
+    #[test]
+    fn test_collapse_whitespace() {
+        assert_eq!(collapse_whitespace("  New \t York\u{00A0} City "), "New York City");
+    }
+
+    #[test]
+    fn test_remove_accents() {
+        // Precomposed and decomposed spellings give the same result
+        assert_eq!(remove_accents("Crème brûlée"), "Creme brulee");
+        assert_eq!(remove_accents("Cre\u{0300}me"), "Creme");
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Cre\u{0300}me", NormalizationForm::Nfc), "Crème");
+        assert_eq!(normalize("\u{FB01}le", NormalizationForm::Nfkc), "file");
+    }
+}
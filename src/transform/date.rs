@@ -5,17 +5,11 @@ use crate::transform::models::ApplyResult;
 
 /// Inspired by: https://stackoverflow.com/a/29387450/1245471
 fn parse_excel_ordinal_date(value: String) -> Option<NaiveDate> {
-    let maybe_ordinal: Option<i64> = value.parse().ok();
-
-    if maybe_ordinal.is_none() {
-        return None
-    }
-
-    let mut ordinal = maybe_ordinal.unwrap();
+    let mut ordinal: i64 = value.parse().ok()?;
     let epoch = NaiveDate::from_ymd(1899, 12, 31);
 
     if ordinal >= 60 {
-        ordinal = ordinal - 1;
+        ordinal -= 1;
     }
 
     Some(epoch + Duration::days(ordinal))
@@ -25,9 +19,9 @@ fn parse_excel_ordinal_date(value: String) -> Option<NaiveDate> {
 pub fn apply_excel_ordinal_date(value: CellValue) -> CellValue {
     CellValue::Date(
         match value {
-            CellValue::String(maybe_content) => maybe_content.map(
-                |content| parse_excel_ordinal_date(content)
-            ).unwrap_or(None),
+            CellValue::String(maybe_content) => maybe_content.and_then(
+                parse_excel_ordinal_date
+            ),
 
             _ => panic!("Runtime typing error: 'excel_ordinal_date' transformation applied to {:?}.", value),
         }
@@ -35,25 +29,10 @@ pub fn apply_excel_ordinal_date(value: CellValue) -> CellValue {
 }
 
 
-#[cfg(test)]
-mod parse_excel_ordinal_date_tests {
-    use super::*;
-
-    #[cfg(test)]
-    fn test_38142() {
-        let ordinal = 38142;
-        let expected_date = NaiveDate::from_ymd(2004, 4, 6);
-        let date = apply_excel_ordinal_date(ordinal.to_string()).unwrap();
-
-        assert_eq!(date, expected_date);
-    }
-}
-
-
-fn parse_date_with_format(value: String, format: &String) -> Result<NaiveDate, String> {
+fn parse_date_with_format(value: String, format: &str) -> Result<NaiveDate, String> {
     NaiveDate::parse_from_str(
         value.as_str(),
-        format.as_str(),
+        format,
     ).map_err(
         |_err| format!(
             "Cannot parse date {} with format {}.",
@@ -63,7 +42,7 @@ fn parse_date_with_format(value: String, format: &String) -> Result<NaiveDate, S
 }
 
 
-pub fn apply_parse_date(value: CellValue, format: &String) -> ApplyResult {
+pub fn apply_parse_date(value: CellValue, format: &str) -> ApplyResult {
     if let CellValue::String(Some(content)) = value {
         parse_date_with_format(content, format).map(
             |date| CellValue::Date(Some(date))
@@ -80,11 +59,11 @@ pub fn apply_parse_date(value: CellValue, format: &String) -> ApplyResult {
 
 fn parse_date_with_formats(
     value: String,
-    formats: &Vec<String>,
+    formats: &[String],
 ) -> Result<NaiveDate, String> {
-    let maybe_date: Option<NaiveDate> = formats.iter().map(
-        |format| parse_date_with_format(value.clone(), &format)
-    ).flatten().next();
+    let maybe_date: Option<NaiveDate> = formats.iter().flat_map(
+        |format| parse_date_with_format(value.clone(), format)
+    ).next();
 
     maybe_date.ok_or(format!(
         "Value '{value}' could not be recognized as date in any of formats: {formats_list}",
@@ -94,7 +73,7 @@ fn parse_date_with_formats(
 }
 
 
-pub fn apply_date_multiple_formats(value: CellValue, formats: &Vec<String>) -> ApplyResult {
+pub fn apply_date_multiple_formats(value: CellValue, formats: &[String]) -> ApplyResult {
     if let CellValue::String(Some(content)) = value {
         parse_date_with_formats(content, formats).map(
             |date| CellValue::Date(Some(date))
@@ -107,3 +86,18 @@ pub fn apply_date_multiple_formats(value: CellValue, formats: &Vec<String>) -> A
         ))
     }
 }
+
+
+#[cfg(test)]
+mod parse_excel_ordinal_date_tests {
+    use super::*;
+
+    #[test]
+    fn test_38142() {
+        let ordinal = 38142;
+        let expected_date = NaiveDate::from_ymd(2004, 6, 4);
+        let date = parse_excel_ordinal_date(ordinal.to_string()).unwrap();
+
+        assert_eq!(date, expected_date);
+    }
+}
@@ -0,0 +1,70 @@
+use serde::Deserialize;
+
+use crate::transform::Separator;
+
+
+/// What to do with empty parts of an exploded value, like the middle one in "a;;b".
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyParts {
+    #[default]
+    Keep,
+    Skip,
+}
+
+
+/// Splits the value of a column into parts; each part gets an output row of its own.
+#[derive(Debug)]
+pub struct Explode {
+    pub separator: Separator,
+    pub on_empty: EmptyParts,
+
+    /// Add a column with the 1-based number of the part right after the exploded one.
+    pub with_index: bool,
+}
+
+
+impl Explode {
+    /// Always returns at least one part, so that no input row disappears from the output.
+    pub fn apply(&self, content: &str) -> Vec<String> {
+        let parts: Vec<String> = self.separator.split(content).into_iter().filter(
+            |part| match self.on_empty {
+                EmptyParts::Keep => true,
+                EmptyParts::Skip => !part.is_empty(),
+            }
+        ).map(
+            |part| part.to_string(),
+        ).collect();
+
+        if parts.is_empty() {
+            vec![String::new()]
+        } else {
+            parts
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explode(on_empty: EmptyParts) -> Explode {
+        Explode {
+            separator: Separator::Literal(";".to_string()),
+            on_empty,
+            with_index: false,
+        }
+    }
+
+    #[test]
+    fn test_keep_empty_parts() {
+        assert_eq!(explode(EmptyParts::Keep).apply("a;;b"), vec!["a", "", "b"]);
+    }
+
+    #[test]
+    fn test_skip_empty_parts() {
+        assert_eq!(explode(EmptyParts::Skip).apply("a;;b;"), vec!["a", "b"]);
+        assert_eq!(explode(EmptyParts::Skip).apply(""), vec![""]);
+    }
+}
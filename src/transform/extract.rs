@@ -0,0 +1,127 @@
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+use crate::transform::{ApplyResult, CellValue, MissPolicy};
+
+
+/// Capture group of a regular expression, referred to by number or by name.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum CaptureGroup {
+    Index(usize),
+    Name(String),
+}
+
+
+impl Default for CaptureGroup {
+    fn default() -> Self {
+        CaptureGroup::Index(0)
+    }
+}
+
+
+impl CaptureGroup {
+    pub fn get<'t>(&self, captures: &Captures<'t>) -> Option<&'t str> {
+        match self {
+            CaptureGroup::Index(index) => captures.get(*index),
+            CaptureGroup::Name(name) => captures.name(name),
+        }.map(|found| found.as_str())
+    }
+}
+
+
+fn extract_from(
+    content: &str,
+    pattern: &Regex,
+    group: &CaptureGroup,
+    join: &Option<String>,
+) -> Option<String> {
+    match join {
+        None => pattern.captures(content).map(
+            |captures| group.get(&captures).unwrap_or("").to_string(),
+        ),
+
+        Some(separator) => {
+            let matches: Vec<&str> = pattern.captures_iter(content).map(
+                |captures| group.get(&captures).unwrap_or(""),
+            ).collect();
+
+            if matches.is_empty() {
+                None
+            } else {
+                Some(matches.join(separator))
+            }
+        }
+    }
+}
+
+
+pub fn apply_extract(
+    value: CellValue,
+    pattern: &Regex,
+    group: &CaptureGroup,
+    join: &Option<String>,
+    on_no_match: MissPolicy,
+) -> ApplyResult {
+    let content = match &value {
+        CellValue::String(Some(content)) => content,
+        CellValue::String(None) => return Ok(value),
+
+        _ => return Err(format!(
+            "Warning: cannot apply 'extract' transformation to a {} value '{:?}'.",
+            &value.type_name(),
+            &value,
+        )),
+    };
+
+    match extract_from(content, pattern, group, join) {
+        Some(extracted) => Ok(CellValue::from_string(extracted)),
+
+        None => match on_no_match {
+            MissPolicy::Empty => Ok(CellValue::String(None)),
+            MissPolicy::Keep => Ok(value),
+            MissPolicy::Error => Err(format!(
+                "Value '{}' does not match pattern {}.",
+                content, pattern,
+            )),
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_named_group() {
+        let pattern = Regex::new(r"(?P<zip>\d{5})").unwrap();
+        let group = CaptureGroup::Name("zip".to_string());
+
+        assert_eq!(
+            extract_from("Springfield, IL 62704", &pattern, &group, &None),
+            Some("62704".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_extract_all_joined() {
+        let pattern = Regex::new(r"#(\w+)").unwrap();
+        let group = CaptureGroup::Index(1);
+
+        assert_eq!(
+            extract_from("#rust and #csv", &pattern, &group, &Some(";".to_string())),
+            Some("rust;csv".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_extract_no_match() {
+        let pattern = Regex::new(r"\d+").unwrap();
+
+        assert_eq!(
+            extract_from("nothing here", &pattern, &CaptureGroup::default(), &None),
+            None,
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use regex::Regex;
+
+use crate::transform::{CaptureGroup, Separator};
+use crate::transform::split::pick;
+
+
+/// Spreads the final value of a column chain over several output columns.
+#[derive(Debug)]
+pub enum Fanout {
+    /// One output column per capture group of the first match.
+    Captures { pattern: Regex, groups: Vec<CaptureGroup> },
+
+    /// One output column per part of the split value.
+    Split { separator: Separator, indices: Vec<isize> },
+}
+
+
+impl Fanout {
+    /// Always returns one value per output column; whatever is not found is empty.
+    pub fn apply(&self, content: &str) -> Vec<String> {
+        match self {
+            Fanout::Captures { pattern, groups } => match pattern.captures(content) {
+                Some(captures) => groups.iter().map(
+                    |group| group.get(&captures).unwrap_or("").to_string(),
+                ).collect(),
+
+                None => vec![String::new(); groups.len()],
+            },
+
+            Fanout::Split { separator, indices } => {
+                let parts = separator.split(content);
+
+                indices.iter().map(
+                    |index| pick(&parts, *index).unwrap_or("").to_string(),
+                ).collect()
+            },
+        }
+    }
+}
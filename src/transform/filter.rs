@@ -0,0 +1,133 @@
+use chrono::NaiveDate;
+use csv::ByteRecord;
+use regex::Regex;
+
+
+/// Record a condition looks at.
+#[derive(Debug, Clone, Copy)]
+pub enum Source {
+    Input(usize),
+    Output(usize),
+}
+
+
+#[derive(Debug, Clone, Copy)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+
+impl Comparison {
+    fn holds<T: PartialOrd>(&self, left: T, right: T) -> bool {
+        match self {
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+        }
+    }
+}
+
+
+/// A check of a single value.
+#[derive(Debug)]
+pub enum Test {
+    Equals(String),
+    Matches(Regex),
+    Empty(bool),
+
+    /// Values which are not numbers never pass.
+    Number(Comparison, f64),
+
+    /// Values which cannot be parsed with the format never pass.
+    Date { comparison: Comparison, date: NaiveDate, format: String },
+}
+
+
+impl Test {
+    fn passes(&self, value: &str) -> bool {
+        match self {
+            Test::Equals(expected) => value == expected,
+            Test::Matches(pattern) => pattern.is_match(value),
+            Test::Empty(expected) => value.is_empty() == *expected,
+
+            Test::Number(comparison, number) => value.trim().parse::<f64>().map(
+                |parsed| comparison.holds(parsed, *number),
+            ).unwrap_or(false),
+
+            Test::Date { comparison, date, format } => NaiveDate::parse_from_str(
+                value.trim(),
+                format,
+            ).map(
+                |parsed| comparison.holds(parsed, *date),
+            ).unwrap_or(false),
+        }
+    }
+}
+
+
+/// Condition a row must satisfy to get to the output.
+#[derive(Debug)]
+pub enum Filter {
+    All(Vec<Filter>),
+    Any(Vec<Filter>),
+    Not(Box<Filter>),
+    Test { source: Source, test: Test },
+}
+
+
+impl Filter {
+    pub fn passes(&self, input: &ByteRecord, output: &ByteRecord) -> bool {
+        match self {
+            Filter::All(filters) => filters.iter().all(|filter| filter.passes(input, output)),
+            Filter::Any(filters) => filters.iter().any(|filter| filter.passes(input, output)),
+            Filter::Not(filter) => !filter.passes(input, output),
+
+            Filter::Test { source, test } => {
+                let bytes = match source {
+                    Source::Input(index) => input.get(*index),
+                    Source::Output(index) => output.get(*index),
+                };
+
+                let value = bytes.map(String::from_utf8_lossy).unwrap_or_default();
+
+                test.passes(&value)
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_comparison() {
+        let test = Test::Number(Comparison::GreaterOrEqual, 100.0);
+
+        assert!(test.passes("100"));
+        assert!(test.passes(" 250.5 "));
+        assert!(!test.passes("99.99"));
+        assert!(!test.passes("lots"));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let input = ByteRecord::from(vec!["active", "150"]);
+        let output = ByteRecord::from(vec![""]);
+
+        let filter = Filter::All(vec![
+            Filter::Test { source: Source::Input(0), test: Test::Equals("active".to_string()) },
+            Filter::Not(Box::new(Filter::Any(vec![
+                Filter::Test { source: Source::Output(0), test: Test::Empty(false) },
+                Filter::Test { source: Source::Input(1), test: Test::Number(Comparison::Less, 100.0) },
+            ]))),
+        ]);
+
+        assert!(filter.passes(&input, &output));
+    }
+}
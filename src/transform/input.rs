@@ -13,8 +13,6 @@ fn safe_to_utf8(bytes: &[u8]) -> String {
 
 pub fn apply_input(row: &ByteRecord, index: &usize) -> CellValue {
     CellValue::String(
-        row.get(*index).map(
-        |bytes| safe_to_utf8(bytes),
-        )
+        row.get(*index).map(safe_to_utf8)
     )
 }
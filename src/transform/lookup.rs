@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use csv::{ByteRecord, StringRecord};
+use serde::Deserialize;
+
+use crate::transform::{ApplyResult, CellValue};
+use crate::transform::input::apply_input;
+
+
+/// What to do when a lookup key is not found in the reference table.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MissPolicy {
+    #[default]
+    Empty,
+    Keep,
+    Error,
+}
+
+
+/// Reference table loaded in memory and indexed by its key column(s).
+#[derive(Debug)]
+pub struct LookupTable {
+    name: String,
+    headers: StringRecord,
+    rows: Vec<StringRecord>,
+    index: HashMap<Vec<String>, usize>,
+}
+
+
+impl LookupTable {
+    /// Build the index. If a key occurs more than once, the first row wins.
+    pub fn new(
+        name: String,
+        headers: StringRecord,
+        rows: Vec<StringRecord>,
+        key_columns: &[usize],
+    ) -> LookupTable {
+        let mut index = HashMap::with_capacity(rows.len());
+
+        for (row_number, row) in rows.iter().enumerate() {
+            let key: Vec<String> = key_columns.iter().map(
+                |column| row.get(*column).unwrap_or("").to_string(),
+            ).collect();
+
+            index.entry(key).or_insert(row_number);
+        }
+
+        LookupTable { name, headers, rows, index }
+    }
+
+    pub fn column_index(&self, column_name: &str) -> Option<usize> {
+        self.headers.iter().position(|header| header == column_name)
+    }
+
+    pub fn get(&self, key: &[String], column: usize) -> Option<&str> {
+        self.index.get(key).map(
+            |row_number| self.rows[*row_number].get(column).unwrap_or(""),
+        )
+    }
+}
+
+
+pub fn apply_lookup(
+    value: CellValue,
+    row: &ByteRecord,
+    table: &LookupTable,
+    column: usize,
+    input: &Option<Vec<usize>>,
+    on_miss: MissPolicy,
+) -> ApplyResult {
+    let key: Vec<String> = match input {
+        Some(indices) => indices.iter().map(
+            |index| apply_input(row, index).to_string(),
+        ).collect(),
+
+        None => match &value {
+            CellValue::String(maybe_content) => vec![
+                maybe_content.clone().unwrap_or_default(),
+            ],
+
+            _ => return Err(format!(
+                "Warning: cannot apply 'lookup' transformation to a {} value '{:?}'.",
+                &value.type_name(),
+                &value,
+            )),
+        },
+    };
+
+    match table.get(&key, column) {
+        Some(found) => Ok(CellValue::from_string(found.to_string())),
+
+        None => match on_miss {
+            MissPolicy::Empty => Ok(CellValue::String(None)),
+            MissPolicy::Keep => Ok(value),
+            MissPolicy::Error => Err(format!(
+                "Key '{}' not found in lookup table {}.",
+                key.join(", "),
+                table.name,
+            )),
+        },
+    }
+}
@@ -3,20 +3,56 @@ mod input;
 mod replace;
 mod case;
 mod date;
+mod lookup;
+mod extract;
+mod classify;
+mod clean;
+mod width;
+mod split;
+mod fanout;
+mod explode;
+mod filter;
+mod unpivot;
+mod state;
 
 use csv::ByteRecord;
 
 
 pub use crate::transform::models::{
     Transformer,
+    ColumnChain,
+    ColumnOutput,
     Transformation,
     CellValue,
     ApplyResult,
 };
+pub use crate::transform::lookup::{LookupTable, MissPolicy};
+pub use crate::transform::extract::CaptureGroup;
+pub use crate::transform::clean::NormalizationForm;
+pub use crate::transform::width::{Overflow, PadSide};
+pub use crate::transform::split::Separator;
+pub use crate::transform::fanout::Fanout;
+pub use crate::transform::explode::{EmptyParts, Explode};
+pub use crate::transform::filter::{Comparison, Filter, Source, Test};
+pub use crate::transform::unpivot::Unpivot;
+pub use crate::transform::state::RunState;
 use crate::transform::input::apply_input;
-use crate::transform::replace::{apply_replace, apply_replace_regex};
+use crate::transform::replace::{apply_replace, apply_replace_sequential, apply_replace_regex};
 use crate::transform::case::{apply_change_case, StringCase};
 use crate::transform::date::{apply_parse_date, apply_date_multiple_formats, apply_excel_ordinal_date};
+use crate::transform::lookup::apply_lookup;
+use crate::transform::extract::apply_extract;
+use crate::transform::classify::apply_classify;
+use crate::transform::clean::{apply_cleanup, Cleanup};
+use crate::transform::width::{apply_pad, apply_slice, apply_truncate};
+use crate::transform::split::apply_split;
+use crate::transform::state::{
+    apply_change_flag,
+    apply_fill_down,
+    apply_lag,
+    apply_row_number,
+    apply_running_sum,
+};
 
 
 fn apply_line_number(line_number: usize) -> CellValue {
@@ -39,17 +75,45 @@ impl Transformation {
         value: CellValue,
         row: &ByteRecord,
         line_number: usize,
+        state: &mut RunState,
     ) -> ApplyResult {
         match self {
             Transformation::Input(index) => Ok(apply_input(row, index)),
 
-            // FIXME: this is a no-op still
-            Transformation::Slice { start: _start, end: _end } => Ok(value),
+            Transformation::Slice { start, end } => apply_slice(value, *start, *end),
+            Transformation::Pad { width, side, fill } => apply_pad(value, *width, *side, *fill),
+            Transformation::Truncate { width, on_overflow } => apply_truncate(
+                value, *width, *on_overflow,
+            ),
+
+            Transformation::Split { separator, index } => apply_split(value, separator, *index),
 
             Transformation::Lowercase => apply_change_case(value, StringCase::Lowercase),
             Transformation::Uppercase => apply_change_case(value, StringCase::Uppercase),
+            Transformation::TitleCase { exceptions } => apply_change_case(
+                value, StringCase::Title(exceptions),
+            ),
+            Transformation::SentenceCase => apply_change_case(value, StringCase::Sentence),
+            Transformation::SnakeCase => apply_change_case(value, StringCase::Snake),
+            Transformation::KebabCase => apply_change_case(value, StringCase::Kebab),
+            Transformation::CamelCase => apply_change_case(value, StringCase::Camel),
+
+            Transformation::Strip => apply_cleanup(value, Cleanup::Strip),
+            Transformation::CollapseWhitespace => apply_cleanup(value, Cleanup::CollapseWhitespace),
+            Transformation::Normalize { form } => apply_cleanup(value, Cleanup::Normalize(*form)),
+            Transformation::RemoveAccents => apply_cleanup(value, Cleanup::RemoveAccents),
+            Transformation::RemoveControlChars => apply_cleanup(value, Cleanup::RemoveControlChars),
+            Transformation::ReplaceNbsp => apply_cleanup(value, Cleanup::ReplaceNbsp),
+
+            Transformation::Replace {
+                automaton, replacements,
+            } => Ok(apply_replace(
+                value,
+                automaton,
+                replacements,
+            )),
 
-            Transformation::Replace { replace } => Ok(apply_replace(
+            Transformation::ReplaceSequential { replace } => Ok(apply_replace_sequential(
                 value,
                 replace,
             )),
@@ -62,6 +126,14 @@ impl Transformation {
                 replace,
             )),
 
+            Transformation::Extract {
+                pattern, group, join, on_no_match,
+            } => apply_extract(value, pattern, group, join, *on_no_match),
+
+            Transformation::Classify {
+                patterns, values, default,
+            } => apply_classify(value, patterns, values, default),
+
             Transformation::Value { value } => Ok(CellValue::from_string(
                 value.clone(),
             )),
@@ -75,6 +147,30 @@ impl Transformation {
                 value, formats,
             ),
             Transformation::ExcelOrdinalDate => Ok(apply_excel_ordinal_date(value)),
+
+            Transformation::Lookup {
+                table, column, input, on_miss,
+            } => apply_lookup(value, row, table, *column, input, *on_miss),
+
+            Transformation::FillDown { slot, reset_on } => apply_fill_down(
+                value, row, reset_on, state.fill_down(*slot),
+            ),
+
+            Transformation::Lag { slot, column, offset, by } => Ok(apply_lag(
+                row, *column, *offset, state.lag(*slot, row, by),
+            )),
+
+            Transformation::RunningSum { slot, by } => apply_running_sum(
+                value, state.running_sum(*slot, row, by),
+            ),
+
+            Transformation::RowNumberBy { slot, by } => Ok(apply_row_number(
+                state.row_number(*slot, row, by),
+            )),
+
+            Transformation::ChangeFlag { slot, by } => Ok(apply_change_flag(
+                value, state.change_flag(*slot, row, by),
+            )),
         }
     }
 }
@@ -1,15 +1,45 @@
+use std::fmt;
+use std::sync::Arc;
+
+use aho_corasick::AhoCorasick;
 use chrono::NaiveDate;
 use csv::StringRecord;
 use linked_hash_map::LinkedHashMap;
-use regex::Regex;
+use regex::{Regex, RegexSet};
+
+use crate::transform::lookup::{LookupTable, MissPolicy};
+use crate::transform::extract::CaptureGroup;
+use crate::transform::clean::NormalizationForm;
+use crate::transform::case::TitleCaseExceptions;
+use crate::transform::width::{Overflow, PadSide};
+use crate::transform::split::Separator;
+use crate::transform::fanout::Fanout;
+use crate::transform::explode::Explode;
+use crate::transform::filter::Filter;
+use crate::transform::unpivot::Unpivot;
 
 
 #[derive(Debug)]
 pub enum Transformation {
     Input(usize),
     Slice { start: usize, end: usize },
-    Replace { replace: LinkedHashMap<String, String> },
+    Pad { width: usize, side: PadSide, fill: char },
+    Truncate { width: usize, on_overflow: Overflow },
+    Split { separator: Separator, index: isize },
+    Replace { automaton: Box<AhoCorasick>, replacements: Vec<String> },
+    ReplaceSequential { replace: LinkedHashMap<String, String> },
     ReplaceRegex { pattern: Regex, replace: String },
+    Extract {
+        pattern: Regex,
+        group: CaptureGroup,
+        join: Option<String>,
+        on_no_match: MissPolicy,
+    },
+    Classify {
+        patterns: RegexSet,
+        values: Vec<String>,
+        default: Option<String>,
+    },
     Value { value: String },
     From { from: String },
 
@@ -17,9 +47,58 @@ pub enum Transformation {
     DateMultiple { formats: Vec<String> },
     ExcelOrdinalDate,
 
+    Lookup {
+        table: Arc<LookupTable>,
+        column: usize,
+        input: Option<Vec<usize>>,
+        on_miss: MissPolicy,
+    },
+
+    FillDown { slot: usize, reset_on: Vec<usize> },
+    Lag { slot: usize, column: usize, offset: usize, by: Vec<usize> },
+    RunningSum { slot: usize, by: Vec<usize> },
+    RowNumberBy { slot: usize, by: Vec<usize> },
+    ChangeFlag { slot: usize, by: Vec<usize> },
+
     Uppercase,
     Lowercase,
+    TitleCase { exceptions: TitleCaseExceptions },
+    SentenceCase,
+    SnakeCase,
+    KebabCase,
+    CamelCase,
     LineNumber,
+
+    Strip,
+    CollapseWhitespace,
+    Normalize { form: NormalizationForm },
+    RemoveAccents,
+    RemoveControlChars,
+    ReplaceNbsp,
+}
+
+
+impl Transformation {
+    /// Input columns the transformation reads, besides the value it is given.
+    pub fn input_columns(&self) -> Vec<usize> {
+        match self {
+            Transformation::Input(index) => vec![*index],
+            Transformation::Lookup { input, .. } => input.clone().unwrap_or_default(),
+            Transformation::FillDown { reset_on, .. } => reset_on.clone(),
+
+            Transformation::Lag { column, by, .. } => {
+                let mut columns = vec![*column];
+                columns.extend(by);
+                columns
+            },
+
+            Transformation::RunningSum { by, .. }
+            | Transformation::RowNumberBy { by, .. }
+            | Transformation::ChangeFlag { by, .. } => by.clone(),
+
+            _ => vec![],
+        }
+    }
 }
 
 
@@ -49,26 +128,53 @@ impl CellValue {
         CellValue::String(Some(value))
     }
 
-    pub fn to_string(&self) -> String {
-        let empty_string = "".to_string();
+}
+
 
+impl fmt::Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            CellValue::String(maybe_value) => maybe_value.as_ref().unwrap_or(
-                &empty_string,
-            ).clone(),
-
-            CellValue::Date(maybe_value) => maybe_value.map(
-                |naive_date| naive_date.to_string(),
-            ).unwrap_or(
-                empty_string,
+            CellValue::String(maybe_value) => write!(
+                f, "{}", maybe_value.as_deref().unwrap_or(""),
             ),
+
+            CellValue::Date(maybe_value) => match maybe_value {
+                Some(naive_date) => write!(f, "{}", naive_date),
+                None => Ok(()),
+            },
         }
     }
 }
 
 
+/// Where the final value of a column chain goes.
+#[derive(Debug)]
+pub enum ColumnOutput {
+    /// To one output column.
+    Single,
+
+    /// Spread over several output columns.
+    Fanout(Fanout),
+
+    /// Split into parts, each of which gets an output row of its own.
+    Explode(Explode),
+}
+
+
+/// Transformations producing one column definition of the config.
+#[derive(Debug)]
+pub struct ColumnChain {
+    pub transformations: Vec<Transformation>,
+    pub output: ColumnOutput,
+}
+
+
 #[derive(Debug)]
 pub struct Transformer {
+    /// Turns every input row into several before the columns are computed.
+    pub unpivot: Option<Unpivot>,
+
     pub headers: StringRecord,
-    pub columns: Vec<Vec<Transformation>>,
+    pub columns: Vec<ColumnChain>,
+    pub filter: Option<Filter>,
 }
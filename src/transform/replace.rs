@@ -1,4 +1,5 @@
 use crate::transform::CellValue;
+use aho_corasick::AhoCorasick;
 use linked_hash_map::LinkedHashMap;
 use regex::Regex;
 
@@ -15,6 +16,26 @@ fn replace_with_mapping(value: String, mapping: &LinkedHashMap<String, String>)
 
 
 pub fn apply_replace(
+    value: CellValue,
+    automaton: &AhoCorasick,
+    replacements: &[String],
+) -> CellValue {
+    CellValue::String(
+        match value {
+            CellValue::String(maybe_content) => maybe_content.map(
+                |content| automaton.replace_all(
+                    content.as_str(),
+                    replacements,
+                )
+            ),
+
+            _ => panic!("Runtime typing error: 'replace' transformation applied to {:?}.", value)
+        }
+    )
+}
+
+
+pub fn apply_replace_sequential(
     value: CellValue,
     mapping: &LinkedHashMap<String, String>,
 ) -> CellValue {
@@ -36,14 +57,14 @@ pub fn apply_replace(
 pub fn apply_replace_regex(
     value: CellValue,
     regex: &Regex,
-    replace: &String,
+    replace: &str,
 ) -> CellValue {
     CellValue::String(
         match value {
             CellValue::String(maybe_content) => maybe_content.map(
                 |content| regex.replace_all(
                         content.as_str(),
-                        replace.as_str(),
+                        replace,
                     ).to_string()
             ),
 
@@ -0,0 +1,77 @@
+use regex::Regex;
+
+use crate::transform::{ApplyResult, CellValue};
+
+
+/// How a cell value is cut into parts.
+#[derive(Debug)]
+pub enum Separator {
+    Literal(String),
+    Pattern(Regex),
+}
+
+
+impl Separator {
+    pub fn split<'t>(&self, content: &'t str) -> Vec<&'t str> {
+        match self {
+            Separator::Literal(separator) => content.split(separator.as_str()).collect(),
+            Separator::Pattern(pattern) => pattern.split(content).collect(),
+        }
+    }
+}
+
+
+/// Pick a part by index; negative indices count from the end, so -1 is the last part.
+pub fn pick<'t>(parts: &[&'t str], index: isize) -> Option<&'t str> {
+    let position = if index < 0 {
+        parts.len().checked_sub(index.unsigned_abs())?
+    } else {
+        index as usize
+    };
+
+    parts.get(position).copied()
+}
+
+
+pub fn apply_split(value: CellValue, separator: &Separator, index: isize) -> ApplyResult {
+    match value {
+        CellValue::String(maybe_content) => Ok(CellValue::String(maybe_content.and_then(
+            |content| pick(&separator.split(&content), index).map(
+                |part| part.to_string(),
+            ),
+        ))),
+
+        _ => Err(format!(
+            "Warning: cannot apply 'split' transformation to a {} value '{:?}'.",
+            &value.type_name(),
+            &value,
+        )),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick() {
+        let parts = vec!["a", "b", "c"];
+
+        assert_eq!(pick(&parts, 0), Some("a"));
+        assert_eq!(pick(&parts, -1), Some("c"));
+        assert_eq!(pick(&parts, -3), Some("a"));
+        assert_eq!(pick(&parts, -4), None);
+        assert_eq!(pick(&parts, 3), None);
+    }
+
+    #[test]
+    fn test_split_by_pattern() {
+        let separator = Separator::Pattern(Regex::new(r",\s*|\s+").unwrap());
+
+        assert_eq!(
+            separator.split("Springfield, IL 62704"),
+            vec!["Springfield", "IL", "62704"],
+        );
+    }
+}
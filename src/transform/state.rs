@@ -0,0 +1,260 @@
+use std::collections::{HashMap, VecDeque};
+
+use csv::ByteRecord;
+
+use crate::decimal::Decimal;
+use crate::transform::{ApplyResult, CellValue};
+
+
+/// What a `fill_down` step remembers.
+#[derive(Default)]
+pub struct FillDownState {
+    last: Option<String>,
+    key: Vec<Vec<u8>>,
+}
+
+
+/// Values of the input columns a step groups rows by.
+type GroupKey = Vec<Vec<u8>>;
+
+/// State of one step, for every group of rows.
+type Groups<T> = HashMap<GroupKey, T>;
+
+
+/// Memory of the stateful steps, kept from row to row for the whole run, across input files.
+/// Every stateful step has a slot number given to it at compile time. Rows dropped later
+/// by the filter have already been counted.
+#[derive(Default)]
+pub struct RunState {
+    fill_down: HashMap<usize, FillDownState>,
+    lag: HashMap<usize, Groups<VecDeque<Vec<u8>>>>,
+    running_sum: HashMap<usize, Groups<Decimal>>,
+    row_number: HashMap<usize, Groups<usize>>,
+    change_flag: HashMap<usize, Groups<Option<String>>>,
+}
+
+
+fn group_state<'a, T: Default>(
+    slots: &'a mut HashMap<usize, Groups<T>>,
+    slot: usize,
+    row: &ByteRecord,
+    by: &[usize],
+) -> &'a mut T {
+    slots.entry(slot).or_default().entry(key_of(row, by)).or_default()
+}
+
+
+impl RunState {
+    pub fn fill_down(&mut self, slot: usize) -> &mut FillDownState {
+        self.fill_down.entry(slot).or_default()
+    }
+
+    pub fn lag(&mut self, slot: usize, row: &ByteRecord, by: &[usize]) -> &mut VecDeque<Vec<u8>> {
+        group_state(&mut self.lag, slot, row, by)
+    }
+
+    pub fn running_sum(&mut self, slot: usize, row: &ByteRecord, by: &[usize]) -> &mut Decimal {
+        group_state(&mut self.running_sum, slot, row, by)
+    }
+
+    pub fn row_number(&mut self, slot: usize, row: &ByteRecord, by: &[usize]) -> &mut usize {
+        group_state(&mut self.row_number, slot, row, by)
+    }
+
+    /// The previous value is None before the first row of a group.
+    pub fn change_flag(
+        &mut self,
+        slot: usize,
+        row: &ByteRecord,
+        by: &[usize],
+    ) -> &mut Option<String> {
+        group_state(&mut self.change_flag, slot, row, by)
+    }
+}
+
+
+fn key_of(row: &ByteRecord, columns: &[usize]) -> GroupKey {
+    columns.iter().map(
+        |index| row.get(*index).unwrap_or(b"").to_vec(),
+    ).collect()
+}
+
+
+fn type_error(transformation_name: &str, value: &CellValue) -> String {
+    format!(
+        "Warning: cannot apply '{}' transformation to a {} value '{:?}'.",
+        transformation_name,
+        value.type_name(),
+        value,
+    )
+}
+
+
+/// Replace an empty value with the last non-empty one. The memory is cleared when the
+/// values of the `reset_on` input columns change. Those are often blank below the first
+/// row of a group themselves, so a blank there does not count as a change.
+pub fn apply_fill_down(
+    value: CellValue,
+    row: &ByteRecord,
+    reset_on: &[usize],
+    state: &mut FillDownState,
+) -> ApplyResult {
+    state.key.resize(reset_on.len(), vec![]);
+
+    for (index, remembered) in reset_on.iter().zip(state.key.iter_mut()) {
+        let current = row.get(*index).unwrap_or(b"");
+
+        if !current.is_empty() && current != remembered.as_slice() {
+            *remembered = current.to_vec();
+            state.last = None;
+        }
+    }
+
+    match value {
+        CellValue::String(Some(content)) if !content.is_empty() => {
+            state.last = Some(content.clone());
+            Ok(CellValue::String(Some(content)))
+        },
+
+        CellValue::String(_) => Ok(CellValue::String(state.last.clone())),
+
+        CellValue::Date(_) => Err(type_error("fill_down", &value)),
+    }
+}
+
+
+/// Value of an input column `offset` rows back; empty for the first rows.
+pub fn apply_lag(
+    row: &ByteRecord,
+    column: usize,
+    offset: usize,
+    history: &mut VecDeque<Vec<u8>>,
+) -> CellValue {
+    history.push_back(row.get(column).unwrap_or(b"").to_vec());
+
+    let value = if history.len() > offset {
+        history.pop_front()
+    } else {
+        None
+    };
+
+    CellValue::from_string(value.map(
+        |bytes| String::from_utf8_lossy(&bytes).into_owned(),
+    ).unwrap_or_default())
+}
+
+
+/// Sum of the values so far, this one included. Empty values add nothing.
+pub fn apply_running_sum(value: CellValue, sum: &mut Decimal) -> ApplyResult {
+    match &value {
+        CellValue::String(maybe_content) => {
+            let content = maybe_content.as_deref().unwrap_or("").trim();
+
+            if !content.is_empty() {
+                let number = Decimal::parse(content).ok_or(format!(
+                    "Warning: '{}' is not a number; cannot add it to the running sum.",
+                    content,
+                ))?;
+
+                *sum = sum.checked_add(number).ok_or(format!(
+                    "Warning: '{}' makes the running sum too large.",
+                    content,
+                ))?;
+            }
+
+            Ok(CellValue::from_string(sum.to_string()))
+        },
+
+        CellValue::Date(_) => Err(type_error("running_sum", &value)),
+    }
+}
+
+
+/// Number of the row within its group, starting from 1.
+pub fn apply_row_number(row_number: &mut usize) -> CellValue {
+    *row_number += 1;
+    CellValue::from_string(row_number.to_string())
+}
+
+
+/// "1" if the value differs from the one in the previous row (the first row included), "0" if not.
+pub fn apply_change_flag(value: CellValue, previous: &mut Option<String>) -> CellValue {
+    let current = value.to_string();
+    let changed = previous.as_ref() != Some(&current);
+
+    *previous = Some(current);
+
+    CellValue::from_string(if changed { "1" } else { "0" }.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill_down(rows: &[(&str, &str)], reset_on: &[usize]) -> Vec<String> {
+        let mut state = FillDownState::default();
+
+        rows.iter().map(|(group, value)| apply_fill_down(
+            CellValue::from_string(value.to_string()),
+            &ByteRecord::from(vec![*group, *value]),
+            reset_on,
+            &mut state,
+        ).unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn test_fill_down() {
+        let rows = [("a", "x"), ("", ""), ("b", ""), ("", "y"), ("", "")];
+
+        assert_eq!(fill_down(&rows, &[]), vec!["x", "x", "x", "y", "y"]);
+        assert_eq!(fill_down(&rows, &[0]), vec!["x", "x", "", "y", "y"]);
+    }
+
+    #[test]
+    fn test_windows_by_group() {
+        let mut state = RunState::default();
+        let rows = [("a", "1"), ("b", "10"), ("a", "2"), ("a", "2"), ("b", "")];
+
+        let results: Vec<Vec<String>> = rows.iter().map(|(group, value)| {
+            let row = ByteRecord::from(vec![*group, *value]);
+            let by = [0];
+
+            let lag = apply_lag(&row, 1, 1, state.lag(0, &row, &by));
+            let sum = apply_running_sum(
+                CellValue::from_string(value.to_string()),
+                state.running_sum(1, &row, &by),
+            ).unwrap();
+            let number = apply_row_number(state.row_number(2, &row, &by));
+            let flag = apply_change_flag(
+                CellValue::from_string(value.to_string()),
+                state.change_flag(3, &row, &by),
+            );
+
+            vec![lag.to_string(), sum.to_string(), number.to_string(), flag.to_string()]
+        }).collect();
+
+        assert_eq!(results, vec![
+            vec!["", "1", "1", "1"],
+            vec!["", "10", "1", "1"],
+            vec!["1", "3", "2", "1"],
+            vec!["2", "5", "3", "0"],
+            vec!["10", "10", "2", "1"],
+        ]);
+    }
+
+    #[test]
+    fn test_running_sum_keeps_decimals() {
+        let mut sum = Decimal::default();
+
+        let sums: Vec<String> = ["0.1", "0.2", "", "-0.30"].iter().map(
+            |value| apply_running_sum(
+                CellValue::from_string(value.to_string()),
+                &mut sum,
+            ).unwrap().to_string(),
+        ).collect();
+
+        assert_eq!(sums, vec!["0.1", "0.3", "0.3", "0.00"]);
+        assert!(apply_running_sum(CellValue::from_string("abc".to_string()), &mut sum).is_err());
+    }
+}
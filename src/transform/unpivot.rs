@@ -0,0 +1,69 @@
+use csv::ByteRecord;
+
+
+/// Turns a set of input columns into rows: one per column, holding the column's header
+/// and value next to the input columns which are kept as they are.
+#[derive(Debug)]
+pub struct Unpivot {
+    /// Input columns kept in every row.
+    pub kept: Vec<usize>,
+
+    /// Header and position of every input column turned into a row.
+    pub unpivoted: Vec<(Vec<u8>, usize)>,
+
+    /// Do not produce rows for empty values.
+    pub skip_empty: bool,
+}
+
+
+impl Unpivot {
+    /// Rows have the kept columns first, then the header and the value.
+    pub fn apply(&self, record: &ByteRecord) -> Vec<ByteRecord> {
+        self.unpivoted.iter().filter_map(
+            |(header, index)| {
+                let value = record.get(*index).unwrap_or(b"");
+
+                if self.skip_empty && value.is_empty() {
+                    return None;
+                }
+
+                let mut row: ByteRecord = self.kept.iter().map(
+                    |index| record.get(*index).unwrap_or(b""),
+                ).collect();
+
+                row.push_field(header);
+                row.push_field(value);
+
+                Some(row)
+            }
+        ).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unpivot() {
+        let unpivot = Unpivot {
+            kept: vec![0],
+            unpivoted: vec![(b"2020-01".to_vec(), 1), (b"2020-02".to_vec(), 2)],
+            skip_empty: true,
+        };
+
+        assert_eq!(
+            unpivot.apply(&ByteRecord::from(vec!["north", "10", "20"])),
+            vec![
+                ByteRecord::from(vec!["north", "2020-01", "10"]),
+                ByteRecord::from(vec!["north", "2020-02", "20"]),
+            ],
+        );
+
+        assert_eq!(
+            unpivot.apply(&ByteRecord::from(vec!["south", "", "5"])),
+            vec![ByteRecord::from(vec!["south", "2020-02", "5"])],
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use serde::Deserialize;
+
+use crate::transform::{ApplyResult, CellValue};
+
+
+/// Which side of the value the fill characters go to.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PadSide {
+    Left,
+    #[default]
+    Right,
+}
+
+
+/// What to do with a value longer than the allowed width.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    #[default]
+    Cut,
+    Error,
+}
+
+
+/// Characters from `start` up to (not including) `end`. Counts characters, not bytes.
+fn slice(content: &str, start: usize, end: usize) -> String {
+    content.chars().skip(start).take(end.saturating_sub(start)).collect()
+}
+
+
+fn pad(content: String, width: usize, side: PadSide, fill: char) -> String {
+    let length = content.chars().count();
+
+    if length >= width {
+        return content;
+    }
+
+    let padding: String = std::iter::repeat_n(fill, width - length).collect();
+
+    match side {
+        PadSide::Left => padding + &content,
+        PadSide::Right => content + &padding,
+    }
+}
+
+
+fn type_error(transformation_name: &str, value: &CellValue) -> String {
+    format!(
+        "Warning: cannot apply '{}' transformation to a {} value '{:?}'.",
+        transformation_name,
+        value.type_name(),
+        value,
+    )
+}
+
+
+pub fn apply_slice(value: CellValue, start: usize, end: usize) -> ApplyResult {
+    match value {
+        CellValue::String(maybe_content) => Ok(CellValue::String(maybe_content.map(
+            |content| slice(&content, start, end),
+        ))),
+
+        _ => Err(type_error("trim", &value)),
+    }
+}
+
+
+pub fn apply_pad(value: CellValue, width: usize, side: PadSide, fill: char) -> ApplyResult {
+    match value {
+        CellValue::String(maybe_content) => Ok(CellValue::String(Some(pad(
+            maybe_content.unwrap_or_default(),
+            width,
+            side,
+            fill,
+        )))),
+
+        _ => Err(type_error("pad", &value)),
+    }
+}
+
+
+pub fn apply_truncate(value: CellValue, width: usize, on_overflow: Overflow) -> ApplyResult {
+    match (&value, on_overflow) {
+        (CellValue::String(_), Overflow::Cut) => apply_slice(value, 0, width),
+
+        (CellValue::String(Some(content)), Overflow::Error) if content.chars().count() > width => Err(
+            format!(
+                "Value '{}' is longer than {} characters.",
+                content, width,
+            )
+        ),
+
+        (CellValue::String(_), Overflow::Error) => Ok(value),
+
+        _ => Err(type_error("truncate", &value)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_counts_characters() {
+        assert_eq!(slice("Zürich", 0, 3), "Zür");
+        assert_eq!(slice("Zürich", 2, 100), "rich");
+        assert_eq!(slice("Zürich", 4, 2), "");
+    }
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(pad("42".to_string(), 5, PadSide::Left, '0'), "00042");
+        assert_eq!(pad("Zü".to_string(), 4, PadSide::Right, '.'), "Zü..");
+        assert_eq!(pad("123456".to_string(), 4, PadSide::Left, '0'), "123456");
+    }
+}
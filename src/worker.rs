@@ -1,10 +1,16 @@
 use std::{io, thread};
-use csv::{ByteRecord, ReaderBuilder, Reader};
+use std::fs::File;
+use std::path::{Component, Path, PathBuf};
+use csv::{ByteRecord, StringRecord};
 
-use crate::compile::create_transformer;
-use crate::transform::{Transformer, Transformation, CellValue, ApplyResult};
+use crate::compile::{create_transformer, LookupTables, OutputSpec, Sources};
+use crate::join::join;
+use crate::output::Output;
+use crate::transform::{Transformer, Transformation, CellValue, ApplyResult, ColumnOutput, RunState};
 use crate::options::Options;
-use crate::writer::writer_thread;
+use crate::partition::PartitionedWriter;
+use crate::reader::read_csv;
+use crate::writer::{writer_thread, Destination};
 
 type TransformationsChain = Vec<Transformation>;
 pub type MaybeTransformationsChain = Result<TransformationsChain, String>;
@@ -18,16 +24,15 @@ fn apply_transformations_chain(
     transformations_chain: &TransformationsChain,
     record: &ByteRecord,
     line_number: usize,
+    state: &mut RunState,
 ) -> String {
-    // TODO it would be useful for performance to stop fold()-ing when the value is Err().
-    let apply_result: ApplyResult = transformations_chain.iter().fold(
-        Ok(CellValue::empty_string()),
-        |result, transformation| result.and_then(
-            |cell_value| transformation.apply(
-                cell_value,
-                record,
-                line_number,
-            )
+    let apply_result: ApplyResult = transformations_chain.iter().try_fold(
+        CellValue::empty_string(),
+        |cell_value, transformation| transformation.apply(
+            cell_value,
+            record,
+            line_number,
+            state,
         )
     );
 
@@ -40,93 +45,278 @@ fn apply_transformations_chain(
 }
 
 
+/// Transform one input record. Usually this gives one output record, but an exploded
+/// column gives one per part of its value.
 fn transform(
-    record: ByteRecord,
+    record: &ByteRecord,
     transformer: &Transformer,
     line_number: usize,
-) -> ByteRecord {
-    let output: Vec<String> = transformer.columns.iter().map(
-        |column| apply_transformations_chain(
-            column,
-            &record,
+    state: &mut RunState,
+) -> Vec<ByteRecord> {
+    let mut output: Vec<String> = Vec::with_capacity(transformer.headers.len());
+
+    // Position of the exploded column in the output, the parts, and whether to number them
+    let mut explosion: Option<(usize, Vec<String>, bool)> = None;
+
+    for column in transformer.columns.iter() {
+        let value = apply_transformations_chain(
+            &column.transformations,
+            record,
             line_number,
-        )
-    ).collect();
+            state,
+        );
+
+        match &column.output {
+            ColumnOutput::Single => output.push(value),
+            ColumnOutput::Fanout(fanout) => output.extend(fanout.apply(&value)),
+
+            ColumnOutput::Explode(explode) => {
+                explosion = Some((output.len(), explode.apply(&value), explode.with_index));
+
+                // Placeholders, to be filled for every part
+                output.push(String::new());
+                if explode.with_index {
+                    output.push(String::new());
+                }
+            },
+        }
+    }
+
+    match explosion {
+        None => vec![ByteRecord::from(output)],
+
+        Some((position, parts, with_index)) => parts.into_iter().enumerate().map(
+            |(index, part)| {
+                let mut exploded_output = output.clone();
+                exploded_output[position] = part;
 
-    ByteRecord::from(output)
+                if with_index {
+                    exploded_output[position + 1] = (index + 1).to_string();
+                }
+
+                ByteRecord::from(exploded_output)
+            }
+        ).collect(),
+    }
+}
+
+
+/// One output of the run, with the memory of its stateful steps.
+struct Pipeline<'a> {
+    name: Option<&'a str>,
+    state: RunState,
+    output: Output,
+
+    /// Output headers of the first input; every other input must give the same.
+    headers: Option<StringRecord>,
+
+    /// Rows the filter dropped over all the input files, if there is a filter.
+    dropped_count: Option<usize>,
 }
 
 
-/// Read and process all the records from given CSV Reader object.
-pub fn process_from_reader<T: io::Read>(
-    mut reader: Reader<T>,
+impl Pipeline<'_> {
+    /// Flush the output and report the rows the filter dropped over the whole run, if any.
+    fn finish(self) -> Result<(), String> {
+        self.output.finish()?;
+
+        if let Some(dropped_count) = self.dropped_count.filter(|count| *count > 0) {
+            match self.name {
+                Some(name) => eprintln!("Filter of output '{}' dropped {} row(s).", name, dropped_count),
+                None => eprintln!("Filter dropped {} row(s).", dropped_count),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Transform one input record for one output and send the results on.
+fn process_record(
+    record: &ByteRecord,
+    transformer: &Transformer,
+    line_number: usize,
+    pipeline: &mut Pipeline,
+) -> Result<(), String> {
+    let unpivoted: Vec<ByteRecord>;
+    let records = match &transformer.unpivot {
+        Some(unpivot) => {
+            unpivoted = unpivot.apply(record);
+            unpivoted.as_slice()
+        },
+        None => std::slice::from_ref(record),
+    };
+
+    for record in records.iter() {
+        for output_record in transform(
+            record,
+            transformer,
+            line_number,
+            &mut pipeline.state,
+        ) {
+            let passes = transformer.filter.as_ref().is_none_or(
+                |filter| filter.passes(record, &output_record),
+            );
+
+            if passes {
+                pipeline.output.send(output_record)?;
+            } else if let Some(dropped_count) = &mut pipeline.dropped_count {
+                *dropped_count += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Process all the records of one input, given its name and headers.
+fn process_records<I: Iterator<Item = Result<ByteRecord, String>>>(
+    input_name: &str,
+    headers: &StringRecord,
+    records: I,
     options: &Options,
     start_line_number: usize,
+    lookup_tables: &mut LookupTables,
+    pipelines: &mut [Pipeline],
 ) -> Result<usize, String> {
-    let headers = reader.headers().unwrap().clone();
-
-    let transformer = create_transformer(
-        &options.config,
-        &headers,
-        &options.variables,
-    )?;
+    let specs = options.config.output_specs();
 
-    let (tx, rx) = crossbeam_channel::bounded(QUEUE_SIZE);
+    let transformers: Vec<Transformer> = specs.iter().map(
+        |(_, spec)| create_transformer(
+            &options.config,
+            spec,
+            headers,
+            &options.variables,
+            lookup_tables,
+        ),
+    ).collect::<Result<_, String>>()?;
 
-    if start_line_number == 1 {
-        tx.send(transformer.headers.as_byte_record().clone()).unwrap();
+    // Column templates and passthrough make the output columns depend on the input headers;
+    // rows of another shape cannot go to the same output, nor use the same state
+    for (pipeline, transformer) in pipelines.iter().zip(transformers.iter()) {
+        if let Some(first_headers) = &pipeline.headers {
+            if *first_headers != transformer.headers {
+                return Err(format!(
+                    "{} gives the output columns {} while the first input gave {}; \
+                    all the inputs must give the same columns.",
+                    input_name,
+                    transformer.headers.iter().collect::<Vec<_>>().join(","),
+                    first_headers.iter().collect::<Vec<_>>().join(","),
+                ));
+            }
+        }
     }
 
-    let writer_handle = thread::spawn(move || writer_thread(rx));
+    for (pipeline, transformer) in pipelines.iter_mut().zip(transformers.iter()) {
+        pipeline.output.prepare(&transformer.headers, pipeline.headers.is_none())?;
+        pipeline.headers = Some(transformer.headers.clone());
+    }
 
-    let mut current_line_number = start_line_number.clone();
-    for (line_number, result) in reader.byte_records().enumerate() {
-        let record = result.unwrap();
+    let mut current_line_number = start_line_number;
+    for (line_number, result) in records.enumerate() {
+        let record = result?;
 
         current_line_number = start_line_number + line_number;
 
-        tx.send(transform(
-            record,
-            &transformer,
-            current_line_number,
-        )).unwrap();
+        for (pipeline, transformer) in pipelines.iter_mut().zip(transformers.iter()) {
+            process_record(&record, transformer, current_line_number, pipeline)?;
+        }
     }
 
-    // We must close the channel to indicate we are not going to send anything else
-    drop(tx);
+    Ok(current_line_number + 1)
+}
 
-    writer_handle.join().unwrap();
 
-    Ok(current_line_number + 1)
+/// Read and process all the CSV records from given source.
+fn process_from_reader<T: io::Read>(
+    source: T,
+    source_name: &str,
+    options: &Options,
+    start_line_number: usize,
+    lookup_tables: &mut LookupTables,
+    pipelines: &mut [Pipeline],
+) -> Result<usize, String> {
+    let (headers, records) = read_csv(source, &options.config.input)?;
+
+    process_records(
+        source_name,
+        &headers,
+        records,
+        options,
+        start_line_number,
+        lookup_tables,
+        pipelines,
+    )
 }
 
 
-/// Read CSV data from standard input.
-fn process_from_stdin(options: Options) -> Result<(), String> {
-    let reader = ReaderBuilder::new()
-        .flexible(true)
-        .from_reader(io::stdin());
+/// Read the joined rows of the sources named in the config.
+fn process_from_sources(
+    sources: &Sources,
+    options: &Options,
+    lookup_tables: &mut LookupTables,
+    pipelines: &mut [Pipeline],
+) -> Result<(), String> {
+    if options.input_files.is_some() {
+        eprintln!("Warning: input files are ignored, since the config has its own sources.");
+    }
+
+    let (headers, records) = join(sources, &options.config.base_directory)?;
+
+    process_records(
+        "The joined sources",
+        &headers,
+        records,
+        options,
+        1,
+        lookup_tables,
+        pipelines,
+    )?;
+
+    Ok(())
+}
+
 
-    process_from_reader(reader, &options, 1)?;
+/// Read CSV data from standard input.
+fn process_from_stdin(
+    options: &Options,
+    lookup_tables: &mut LookupTables,
+    pipelines: &mut [Pipeline],
+) -> Result<(), String> {
+    process_from_reader(
+        io::stdin(),
+        "The standard input",
+        options,
+        1,
+        lookup_tables,
+        pipelines,
+    )?;
 
     Ok(())
 }
 
 
 /// Read CSV data from a set of files.
-fn process_from_file_list(options: &Options) -> Result<(), String> {
+fn process_from_file_list(
+    options: &Options,
+    lookup_tables: &mut LookupTables,
+    pipelines: &mut [Pipeline],
+) -> Result<(), String> {
     let mut line_number = 1;
     for file_path in options.input_files.as_ref().unwrap().iter() {
-        let reader = ReaderBuilder::new()
-            .flexible(true)
-            .from_path(file_path).map_err(
-                |err| err.to_string(),
-            )?;
+        let file = File::open(file_path).map_err(
+            |err| format!("Cannot open input file {}: {}", file_path, err),
+        )?;
 
         line_number = process_from_reader(
-            reader,
-            &options,
+            file,
+            &format!("Input file {}", file_path),
+            options,
             line_number,
+            lookup_tables,
+            pipelines,
         )?;
     }
 
@@ -134,10 +324,110 @@ fn process_from_file_list(options: &Options) -> Result<(), String> {
 }
 
 
+/// Open the file an output goes to, or the standard output.
+fn open_destination(spec: &OutputSpec, base_directory: &Path) -> Result<Destination, String> {
+    match (&spec.file, &spec.partition_by) {
+        (None, None) => Ok(Destination::Stream(Box::new(io::stdout()))),
+
+        (Some(file), None) => {
+            let path = base_directory.join(file);
+
+            let file = File::create(&path).map_err(
+                |err| format!("Cannot create output file {}: {}", path.display(), err),
+            )?;
+
+            Ok(Destination::Stream(Box::new(file)))
+        },
+
+        (Some(template), Some(columns)) => Ok(Destination::Partitioned(PartitionedWriter::new(
+            template,
+            &columns.to_vec(),
+            base_directory,
+            spec.max_open_files,
+        )?)),
+
+        (None, Some(_)) => Err("'partition_by' needs a 'file' name template.".to_string()),
+    }
+}
+
+
 /// Do the whole job!
 pub fn process(options: Options) -> Result<(), String> {
-    match options.input_files {
-        None => process_from_stdin(options),
-        Some(_) => process_from_file_list(&options),
+    let specs = options.config.output_specs();
+
+    if specs.iter().filter(|(_, spec)| spec.file.is_none()).count() > 1 {
+        return Err(
+            "Only one output can go to the standard output; give the others a 'file'.".to_string(),
+        );
+    }
+
+    // Two outputs creating the same file would overwrite each other's rows
+    let mut paths: Vec<(PathBuf, &str)> = vec![];
+    for (name, spec) in specs.iter() {
+        if let Some(file) = &spec.file {
+            let path: PathBuf = options.config.base_directory.join(file).components().filter(
+                |component| *component != Component::CurDir,
+            ).collect();
+            let name = name.unwrap_or_default();
+
+            if let Some((_, other)) = paths.iter().find(|(other_path, _)| *other_path == path) {
+                return Err(format!(
+                    "Outputs '{}' and '{}' both go to {}; give each output a file of its own.",
+                    other, name, path.display(),
+                ));
+            }
+
+            paths.push((path, name));
+        }
     }
+
+    let destinations = specs.iter().map(
+        |(_, spec)| open_destination(spec, &options.config.base_directory),
+    ).collect::<Result<Vec<_>, String>>()?;
+
+    let mut pipelines: Vec<Pipeline> = vec![];
+    let mut writer_handles = vec![];
+
+    for ((name, spec), destination) in specs.into_iter().zip(destinations) {
+        let (tx, rx) = crossbeam_channel::bounded(QUEUE_SIZE);
+        writer_handles.push(thread::spawn(move || writer_thread(rx, destination)));
+
+        pipelines.push(Pipeline {
+            name,
+            state: RunState::default(),
+            output: Output::new(tx, spec),
+            headers: None,
+            dropped_count: spec.filter.as_ref().map(|_| 0),
+        });
+    }
+
+    // Loaded once and shared by the transformers of every input and output
+    let mut lookup_tables = LookupTables::new(&options.config.base_directory);
+
+    let processed = match (&options.config.sources, &options.input_files) {
+        (Some(sources), _) => process_from_sources(
+            sources,
+            &options,
+            &mut lookup_tables,
+            &mut pipelines,
+        ),
+        (None, None) => process_from_stdin(&options, &mut lookup_tables, &mut pipelines),
+        (None, Some(_)) => process_from_file_list(&options, &mut lookup_tables, &mut pipelines),
+    };
+
+    // Either way, the outputs must be gone to close the channels and let the writers stop
+    let result = match processed {
+        Ok(()) => pipelines.into_iter().try_for_each(Pipeline::finish),
+        Err(err) => {
+            drop(pipelines);
+            Err(err)
+        },
+    };
+
+    // A writer error explains a failure to send records to it better than the failure itself
+    for writer_handle in writer_handles {
+        writer_handle.join().unwrap()?;
+    }
+
+    result
 }
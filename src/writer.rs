@@ -3,16 +3,36 @@ use std::io;
 // use std::sync::mpsc::Receiver;
 use crossbeam_channel::Receiver;
 
+use crate::partition::PartitionedWriter;
+
+
+/// Where an output goes.
+pub enum Destination {
+    Stream(Box<dyn io::Write + Send>),
+    Partitioned(PartitionedWriter),
+}
+
 
 /// Receive data records and print them (usually, to stdout or a file).
 /// FIXME rename this to just `writer`.
-pub fn writer_thread(rx: Receiver<ByteRecord>) {
-    // FIXME switch from hardcoded stdout to a more generic solution
-    let mut writer = Writer::from_writer(io::stdout());
+pub fn writer_thread(rx: Receiver<ByteRecord>, destination: Destination) -> Result<(), String> {
+    match destination {
+        Destination::Stream(stream) => {
+            let mut writer = Writer::from_writer(stream);
 
-    for record in rx {
-        writer.write_record(&record).unwrap();
-    }
+            for record in rx {
+                writer.write_record(&record).map_err(|err| err.to_string())?;
+            }
+
+            writer.flush().map_err(|err| err.to_string())
+        },
 
-    writer.flush().unwrap();
+        Destination::Partitioned(mut writer) => {
+            for record in rx {
+                writer.write(record)?;
+            }
+
+            writer.finish()
+        },
+    }
 }